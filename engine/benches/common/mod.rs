@@ -0,0 +1,50 @@
+// Общие заготовки бенчмарков
+use microdelay_engine::lfo::Shape;
+use microdelay_engine::saturation::Curve;
+use microdelay_engine::{EngineParams, Routing};
+
+pub const SAMPLERATE: f32 = 48000.0;
+pub const BLOCK_SIZE: usize = 512;
+
+// Все линии с модуляцией, насыщением и посылами друг в друга - худший случай для петли
+pub fn busy_matrix(line_count: usize, routing: Routing) -> EngineParams {
+    let mut params = EngineParams {
+        line_count,
+        ..Default::default()
+    };
+    for (line_idx, line) in params.lines.iter_mut().enumerate() {
+        line.level = 50.0;
+        line.delay = 37.0 + 11.3 * line_idx as f32;
+        line.lfo_shape = Shape::Sine;
+        line.lfo_depth = 2.0;
+        line.lowpass = 6000.0;
+        line.drive_curve = Curve::Tanh;
+        line.drive = 6.0;
+        line.pan = if line_idx % 2 == 0 { -50.0 } else { 50.0 };
+        line.routing = routing;
+    }
+    for (from_idx, sends) in params.sends.iter_mut().enumerate() {
+        for (to_idx, send) in sends.iter_mut().enumerate() {
+            *send = if from_idx == to_idx { 40.0 } else { -15.0 };
+        }
+    }
+    params
+}
+
+pub fn noise(len: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            state as f32 / u32::MAX as f32 - 0.5
+        })
+        .collect()
+}
+
+// Имя, число каналов, число линий, маршрутизация
+pub const CONFIGS: [(&str, usize, usize, Routing); 4] = [
+    ("mono_2_lines", 1, 2, Routing::Normal),
+    ("stereo_2_lines", 2, 2, Routing::Normal),
+    ("stereo_8_lines", 2, 8, Routing::Normal),
+    ("stereo_8_lines_ping_pong", 2, 8, Routing::PingPong),
+];
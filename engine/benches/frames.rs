@@ -0,0 +1,40 @@
+// Скорость обработки блока по кадрам: cargo bench -p microdelay_engine --bench frames
+mod common;
+
+use common::{BLOCK_SIZE, CONFIGS, SAMPLERATE, busy_matrix, noise};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use microdelay_engine::{MAX_CHANNELS, MatrixDelayEngine};
+use std::hint::black_box;
+
+fn process_block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("process_block");
+    group.throughput(Throughput::Elements(BLOCK_SIZE as u64));
+    for (name, channels_number, line_count, routing) in CONFIGS {
+        let params = busy_matrix(line_count, routing);
+        let input: Vec<Vec<f32>> = (0..channels_number)
+            .map(|channel_idx| noise(BLOCK_SIZE, channel_idx as u32 + 1))
+            .collect();
+        let mut engine = MatrixDelayEngine::new(channels_number, BLOCK_SIZE, SAMPLERATE);
+
+        // по кадрам, как в плагине через Buffer::iter_samples
+        group.bench_function(BenchmarkId::new("frames", name), |b| {
+            b.iter(|| {
+                engine.prepare_block(channels_number, BLOCK_SIZE, black_box(&params));
+                for sample_idx in 0..BLOCK_SIZE {
+                    let mut frame = [0.0; MAX_CHANNELS];
+                    let frame = &mut frame[..channels_number];
+                    frame
+                        .iter_mut()
+                        .zip(&input)
+                        .for_each(|(value, input)| *value = input[sample_idx]);
+                    engine.process_frame(frame, sample_idx);
+                    black_box(&frame);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, process_block);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+// Скорость обработки блока через срезы каналов: cargo bench -p microdelay_engine
+// Использует только MatrixDelayEngine::process, поэтому собирается и со старыми версиями движка
+// и годится для сравнения через --save-baseline / --baseline
+mod common;
+
+use common::{BLOCK_SIZE, CONFIGS, SAMPLERATE, busy_matrix, noise};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use microdelay_engine::MatrixDelayEngine;
+use std::hint::black_box;
+
+fn process_block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("process_block");
+    group.throughput(Throughput::Elements(BLOCK_SIZE as u64));
+    for (name, channels_number, line_count, routing) in CONFIGS {
+        let params = busy_matrix(line_count, routing);
+        let input: Vec<Vec<f32>> = (0..channels_number)
+            .map(|channel_idx| noise(BLOCK_SIZE, channel_idx as u32 + 1))
+            .collect();
+        let mut engine = MatrixDelayEngine::new(channels_number, BLOCK_SIZE, SAMPLERATE);
+        let mut channels = input.clone();
+
+        // неперемежающиеся каналы, как в рендере
+        group.bench_function(BenchmarkId::new("slices", name), |b| {
+            b.iter(|| {
+                channels
+                    .iter_mut()
+                    .zip(&input)
+                    .for_each(|(channel, input)| channel.copy_from_slice(input));
+                let mut block: Vec<&mut [f32]> = channels
+                    .iter_mut()
+                    .map(|channel| channel.as_mut_slice())
+                    .collect();
+                engine.process(&mut block, black_box(&params));
+                black_box(&channels);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, process_block);
+criterion_main!(benches);
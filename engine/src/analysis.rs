@@ -0,0 +1,84 @@
+// Импульсная и амплитудно-частотная характеристики матрицы для графиков.
+// Отклик считает тот же движок, что и звук, поэтому картинка совпадает с тем, что слышно
+use crate::{EngineParams, MatrixDelayEngine};
+use std::f64::consts::PI;
+
+// Отклик на единичный импульс в обоих каналах, среднее каналов. Движок сбрасывается перед
+// расчетом; LFO, насыщение и ограничитель входят в отклик как есть.
+// Движок должен быть инициализирован на два канала
+pub fn impulse_response(
+    engine: &mut MatrixDelayEngine,
+    params: &EngineParams,
+    len: usize,
+) -> Vec<f32> {
+    engine.reset();
+    let mut left = vec![0.0; len];
+    let mut right = vec![0.0; len];
+    if len > 0 {
+        left[0] = 1.0;
+        right[0] = 1.0;
+    }
+    let block_size = engine.max_block_size();
+    for (left, right) in left
+        .chunks_mut(block_size)
+        .zip(right.chunks_mut(block_size))
+    {
+        engine.process(&mut [left, right], params);
+    }
+    left.iter()
+        .zip(&right)
+        .map(|(left, right)| (left + right) * 0.5)
+        .collect()
+}
+
+// Амплитуда отклика в дБ на частотах k * samplerate / len, k = 0..=len / 2.
+// Длина отклика - степень двойки
+pub fn magnitude_response(impulse_response: &[f32]) -> Vec<f32> {
+    let len = impulse_response.len();
+    assert!(len.is_power_of_two(), "length must be a power of two");
+    let mut re: Vec<f64> = impulse_response.iter().map(|&value| value as f64).collect();
+    let mut im = vec![0.0; len];
+    fft(&mut re, &mut im);
+    re.iter()
+        .zip(&im)
+        .take(len / 2 + 1)
+        .map(|(re, im)| (20.0 * re.hypot(*im).max(1e-9).log10()) as f32)
+        .collect()
+}
+
+// Итеративное БПФ по основанию 2 на месте
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let len = re.len();
+    // перестановка в обратном порядке битов
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let angle = -2.0 * PI / size as f64;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (a, b) = (start + k, start + k + size / 2);
+                let t_re = re[b] * cos - im[b] * sin;
+                let t_im = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size *= 2;
+    }
+}
@@ -0,0 +1,157 @@
+// Линия читается до записи, поэтому петля обратной связи не может быть короче сэмпла
+pub const MIN_DELAY_SAMPLES: f32 = 1.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Hermite,
+    Lagrange,
+    Allpass,
+}
+
+#[derive(Debug, Default)]
+pub struct DelayLine {
+    // время задержки в сэмплах для каждого канала
+    pub delay: Vec<f32>,
+    pub interpolation: Interpolation,
+    pub samplerate: f32,
+    pub channel_delay_buffer: Vec<Vec<f32>>,
+    pub delay_buffer_size: usize,
+    pub current_arrow_pos: Vec<isize>,
+    // последний выход allpass-интерполятора для каждого канала
+    pub allpass_state: Vec<f32>,
+
+    pub delay_automation_samples: Vec<f32>,
+}
+
+impl DelayLine {
+    pub fn init(
+        &mut self,
+        delay_buffer_size: usize,
+        channels_number: usize,
+        max_buffer_size: usize,
+        samplerate: f32,
+    ) {
+        self.delay = vec![MIN_DELAY_SAMPLES; channels_number];
+        self.samplerate = samplerate;
+        self.channel_delay_buffer = vec![vec![0.0; delay_buffer_size]; channels_number];
+        self.delay_buffer_size = delay_buffer_size;
+        self.current_arrow_pos = vec![0; channels_number];
+        self.allpass_state = vec![0.0; channels_number];
+        self.delay_automation_samples = vec![0.0; max_buffer_size];
+    }
+
+    fn value_samples_ago(&self, channel_idx: usize, samples_ago: isize) -> f32 {
+        let idx = (self.current_arrow_pos[channel_idx] - samples_ago)
+            .rem_euclid(self.delay_buffer_size as isize);
+        self.channel_delay_buffer[channel_idx][idx as usize]
+    }
+
+    pub fn read_value_from_channel(&mut self, channel_idx: usize) -> f32 {
+        // чтение идет до записи текущего сэмпла, поэтому x(0) еще не доступен
+        let delay = self.delay[channel_idx];
+        let delay_time_whole_samples = delay.floor() as isize;
+        let interpolation_ratio = delay - delay.floor();
+
+        let x = |samples_ago: isize| self.value_samples_ago(channel_idx, samples_ago);
+        // для задержек короче двух сэмплов левая точка достраивается линейно
+        let previous_point = || {
+            if delay_time_whole_samples > 1 {
+                x(delay_time_whole_samples - 1)
+            } else {
+                2.0 * x(delay_time_whole_samples) - x(delay_time_whole_samples + 1)
+            }
+        };
+
+        match self.interpolation {
+            Interpolation::Linear => crate::utils::convex(
+                x(delay_time_whole_samples + 1),
+                x(delay_time_whole_samples),
+                interpolation_ratio,
+            ),
+            Interpolation::Hermite => crate::utils::hermite(
+                previous_point(),
+                x(delay_time_whole_samples),
+                x(delay_time_whole_samples + 1),
+                x(delay_time_whole_samples + 2),
+                interpolation_ratio,
+            ),
+            Interpolation::Lagrange => crate::utils::lagrange(
+                previous_point(),
+                x(delay_time_whole_samples),
+                x(delay_time_whole_samples + 1),
+                x(delay_time_whole_samples + 2),
+                interpolation_ratio,
+            ),
+            Interpolation::Allpass => {
+                // Фильтр Тирана 1-го порядка стабилен с запасом при дробной части в [0.5, 1.5):
+                // при дробной части 0 полюс ложится на z = -1. Для задержек короче 1.5 сэмпла
+                // x(0) еще не записан, поэтому они читаются линейно, а состояние фильтра
+                // подхватывает значение, чтобы выход из этой зоны был без скачка
+                if interpolation_ratio < 0.5 && delay_time_whole_samples <= 1 {
+                    let value = crate::utils::convex(
+                        x(delay_time_whole_samples + 1),
+                        x(delay_time_whole_samples),
+                        interpolation_ratio,
+                    );
+                    self.allpass_state[channel_idx] = value;
+                    return value;
+                }
+                let (whole, fraction) = if interpolation_ratio < 0.5 {
+                    (delay_time_whole_samples - 1, interpolation_ratio + 1.0)
+                } else {
+                    (delay_time_whole_samples, interpolation_ratio)
+                };
+                let coefficient = (1.0 - fraction) / (1.0 + fraction);
+                let previous = self.allpass_state[channel_idx];
+
+                let value = coefficient * (x(whole) - previous) + x(whole + 1);
+                self.allpass_state[channel_idx] = value;
+                value
+            }
+        }
+    }
+
+    pub fn write_value_to_channel(&mut self, value_to_write: f32, channel_idx: usize) {
+        let arrow_pos = &mut self.current_arrow_pos[channel_idx];
+        let current_delay_buffer = &mut self.channel_delay_buffer[channel_idx];
+
+        current_delay_buffer[*arrow_pos as usize] = value_to_write;
+    }
+
+    pub fn move_arrow_over_channel(&mut self, channel_idx: usize) {
+        if self.current_arrow_pos[channel_idx] >= self.delay_buffer_size as isize - 1 {
+            self.current_arrow_pos[channel_idx] = 0
+        } else {
+            self.current_arrow_pos[channel_idx] += 1
+        }
+    }
+
+    pub fn set_delay(&mut self, delay_in_float_samples: f32) {
+        for channel_idx in 0..self.delay.len() {
+            self.set_channel_delay(delay_in_float_samples, channel_idx);
+        }
+    }
+
+    pub fn set_channel_delay(&mut self, delay_in_float_samples: f32, channel_idx: usize) {
+        // интерполяторам нужно еще два сэмпла за точкой чтения
+        let max_delay = self.delay_buffer_size.saturating_sub(3) as f32;
+        self.delay[channel_idx] = delay_in_float_samples.min(max_delay).max(MIN_DELAY_SAMPLES);
+    }
+
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        if self.interpolation != interpolation {
+            self.interpolation = interpolation;
+            self.allpass_state.fill(0.0);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.channel_delay_buffer
+            .iter_mut()
+            .for_each(|s| s.fill(0.0));
+        self.current_arrow_pos.fill(0);
+        self.allpass_state.fill(0.0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::f32::consts::PI;
+
+pub const MIN_FILTER_CUTOFF: f32 = 20.0; // Hz
+pub const MAX_FILTER_CUTOFF: f32 = 20000.0;
+// на краях диапазона фильтр выключен, а в последней трети октавы к краю плавно
+// уходит в обход, чтобы по умолчанию линия была чистой задержкой
+const BYPASS_FADE: f32 = 1.0 / 3.0; // octaves
+
+// Фильтр в записи линии: ФВЧ и ФНЧ последовательно, оба - SVF с топологией TPT,
+// чтобы резкая автоматизация среза не разваливала состояние
+#[derive(Debug, Default)]
+pub struct FeedbackFilter {
+    // [ic1eq, ic2eq] для каждого канала
+    lowpass_state: Vec<[f32; 2]>,
+    highpass_state: Vec<[f32; 2]>,
+    // доля фильтрованного сигнала на выходе ступени: 0 - обход, 1 - только фильтр
+    lowpass_mix: Vec<f32>,
+    highpass_mix: Vec<f32>,
+
+    // до prepare_block - Гц и добротность, после - коэффициенты g и k
+    pub lowpass_automation_samples: Vec<f32>,
+    pub highpass_automation_samples: Vec<f32>,
+    pub resonance_automation_samples: Vec<f32>,
+}
+
+impl FeedbackFilter {
+    pub fn init(&mut self, channels_number: usize, max_buffer_size: usize) {
+        self.lowpass_state = vec![[0.0; 2]; channels_number];
+        self.highpass_state = vec![[0.0; 2]; channels_number];
+        self.lowpass_automation_samples = vec![0.0; max_buffer_size];
+        self.highpass_automation_samples = vec![0.0; max_buffer_size];
+        self.resonance_automation_samples = vec![0.0; max_buffer_size];
+        self.lowpass_mix = vec![0.0; max_buffer_size];
+        self.highpass_mix = vec![0.0; max_buffer_size];
+    }
+
+    pub fn reset(&mut self) {
+        self.lowpass_state.fill([0.0; 2]);
+        self.highpass_state.fill([0.0; 2]);
+    }
+
+    pub fn prepare_block(&mut self, block_len: usize, samplerate: f32) {
+        // срез уже сглажен по октавам, поэтому и доля фильтра меняется плавно
+        let fade = |octaves_from_edge: f32| (octaves_from_edge / BYPASS_FADE).clamp(0.0, 1.0);
+        for ((lowpass_mix, highpass_mix), (lowpass, highpass)) in self.lowpass_mix[..block_len]
+            .iter_mut()
+            .zip(&mut self.highpass_mix[..block_len])
+            .zip(
+                self.lowpass_automation_samples
+                    .iter()
+                    .zip(&self.highpass_automation_samples),
+            )
+        {
+            *lowpass_mix = fade((MAX_FILTER_CUTOFF / lowpass).log2());
+            *highpass_mix = fade((highpass / MIN_FILTER_CUTOFF).log2());
+        }
+
+        let cutoff_to_g = |cutoff: &mut f32| {
+            *cutoff = (PI * cutoff.min(samplerate * 0.49) / samplerate).tan();
+        };
+        self.lowpass_automation_samples[..block_len]
+            .iter_mut()
+            .for_each(cutoff_to_g);
+        self.highpass_automation_samples[..block_len]
+            .iter_mut()
+            .for_each(cutoff_to_g);
+        self.resonance_automation_samples[..block_len]
+            .iter_mut()
+            .for_each(|q| *q = 1.0 / *q);
+    }
+
+    // Коэффициенты блока от другого фильтра после его prepare_block, состояние остается свое
+    pub fn copy_automation_from(&mut self, other: &Self, block_len: usize) {
+        self.lowpass_automation_samples[..block_len]
+            .copy_from_slice(&other.lowpass_automation_samples[..block_len]);
+        self.highpass_automation_samples[..block_len]
+            .copy_from_slice(&other.highpass_automation_samples[..block_len]);
+        self.resonance_automation_samples[..block_len]
+            .copy_from_slice(&other.resonance_automation_samples[..block_len]);
+        self.lowpass_mix[..block_len].copy_from_slice(&other.lowpass_mix[..block_len]);
+        self.highpass_mix[..block_len].copy_from_slice(&other.highpass_mix[..block_len]);
+    }
+
+    pub fn process(&mut self, input: f32, channel_idx: usize, sample_idx: usize) -> f32 {
+        let k = self.resonance_automation_samples[sample_idx];
+
+        let (_, _, high) = svf_tick(
+            &mut self.highpass_state[channel_idx],
+            input,
+            self.highpass_automation_samples[sample_idx],
+            k,
+        );
+        // фильтры считаются и в обходе, чтобы при выходе из него состояние было живым
+        let high = input + (high - input) * self.highpass_mix[sample_idx];
+        let (low, _, _) = svf_tick(
+            &mut self.lowpass_state[channel_idx],
+            high,
+            self.lowpass_automation_samples[sample_idx],
+            k,
+        );
+        high + (low - high) * self.lowpass_mix[sample_idx]
+    }
+}
+
+// Один шаг SVF (Andrew Simper, Cytomic). Возвращает (ФНЧ, полосовой, ФВЧ)
+#[inline]
+fn svf_tick(state: &mut [f32; 2], input: f32, g: f32, k: f32) -> (f32, f32, f32) {
+    let a1 = 1.0 / (1.0 + g * (g + k));
+    let a2 = g * a1;
+    let a3 = g * a2;
+
+    let v3 = input - state[1];
+    let v1 = a1 * state[0] + a2 * v3;
+    let v2 = state[1] + a2 * state[0] + a3 * v3;
+    state[0] = 2.0 * v1 - state[0];
+    state[1] = 2.0 * v2 - state[1];
+
+    (v2, v1, input - k * v1 - v2)
+}
@@ -0,0 +1,87 @@
+use std::f32::consts::{PI, TAU};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    #[default]
+    Sine,
+    Triangle,
+    Saw,
+    SampleAndHold,
+    SmoothRandom,
+}
+
+#[derive(Debug, Default)]
+pub struct Lfo {
+    pub shape: Shape,
+    pub samplerate: f32,
+    // фаза в периодах [0, 1), у всех каналов растет одинаково
+    pub phase: Vec<f32>,
+    // позиция с учетом стерео-сдвига на прошлом сэмпле, для поиска начала периода
+    last_position: Vec<f32>,
+    held_value: Vec<f32>,
+    previous_held_value: Vec<f32>,
+    random_state: Vec<u32>,
+
+    pub rate_automation_samples: Vec<f32>,
+    pub depth_automation_samples: Vec<f32>,
+}
+
+impl Lfo {
+    pub fn init(&mut self, channels_number: usize, max_buffer_size: usize, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.phase = vec![0.0; channels_number];
+        self.last_position = vec![0.0; channels_number];
+        self.held_value = vec![0.0; channels_number];
+        self.previous_held_value = vec![0.0; channels_number];
+        self.random_state = vec![0; channels_number];
+        self.rate_automation_samples = vec![0.0; max_buffer_size];
+        self.depth_automation_samples = vec![0.0; max_buffer_size];
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.phase.fill(0.0);
+        self.last_position.fill(0.0);
+        self.held_value.fill(0.0);
+        self.previous_held_value.fill(0.0);
+        // разные зерна, чтобы случайные формы в каналах не совпадали
+        self.random_state
+            .iter_mut()
+            .enumerate()
+            .for_each(|(channel_idx, s)| {
+                *s = 0x9E37_79B9 ^ (channel_idx as u32 + 1).wrapping_mul(0x85EB_CA6B)
+            });
+    }
+
+    // Значение в [-1, 1] для канала; phase_offset в периодах добавляется на каждый следующий канал
+    pub fn next_value(&mut self, channel_idx: usize, rate_hz: f32, phase_offset: f32) -> f32 {
+        let position = (self.phase[channel_idx] + phase_offset * channel_idx as f32).fract();
+        if position < self.last_position[channel_idx] {
+            self.previous_held_value[channel_idx] = self.held_value[channel_idx];
+            self.held_value[channel_idx] = self.next_random(channel_idx);
+        }
+        self.last_position[channel_idx] = position;
+
+        let value = match self.shape {
+            Shape::Sine => (TAU * position).sin(),
+            Shape::Triangle => 1.0 - 4.0 * (position - 0.5).abs(),
+            Shape::Saw => 2.0 * position - 1.0,
+            Shape::SampleAndHold => self.held_value[channel_idx],
+            Shape::SmoothRandom => {
+                let ratio = 0.5 - 0.5 * (PI * position).cos();
+                crate::utils::convex(
+                    self.held_value[channel_idx],
+                    self.previous_held_value[channel_idx],
+                    ratio,
+                )
+            }
+        };
+
+        self.phase[channel_idx] = (self.phase[channel_idx] + rate_hz / self.samplerate).fract();
+        value
+    }
+
+    fn next_random(&mut self, channel_idx: usize) -> f32 {
+        crate::utils::white_noise(&mut self.random_state[channel_idx])
+    }
+}
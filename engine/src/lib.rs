@@ -0,0 +1,19 @@
+// Движок MicroDelay: матрица линий задержки с обычным Rust API, без nih_plug и egui.
+// Параметры задаются в единицах ручек плагина, сглаживание движок делает сам
+pub mod analysis;
+pub mod delay_line;
+pub mod filter;
+pub mod lfo;
+mod matrix;
+pub mod saturation;
+pub mod smoother;
+pub mod stability;
+pub mod tempo_sync;
+pub mod tuning;
+pub mod utils;
+pub mod voices;
+
+pub use matrix::{
+    EngineParams, LineSettings, MAX_CHANNELS, MAX_DELAY_TIME, MAX_LFO_DEPTH, MAX_LINES,
+    MAX_STEREO_OFFSET, MIN_DELAY_TIME, MatrixDelayEngine, MidSide, Routing, Stereo, min_delay_time,
+};
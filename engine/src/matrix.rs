@@ -0,0 +1,947 @@
+// Матрица задержек целиком: плагин, standalone и офлайн-рендер гоняют звук через этот код
+use crate::delay_line::{self, Interpolation};
+use crate::lfo::{self, Shape};
+use crate::saturation::{self, Curve};
+use crate::smoother::{Smoother, SmoothingStyle};
+use crate::stability::{self, Safety};
+use crate::tempo_sync::{self, Division, Mode, TransportInfo};
+use crate::tuning::{NoteTuning, TuningSettings};
+use crate::voices::{VoicePool, VoiceTarget};
+use crate::{filter, utils};
+
+pub const MIN_DELAY_TIME: f32 = 0.025; // milliseconds
+pub const MAX_DELAY_TIME: f32 = 16000.0;
+pub const MAX_LFO_DEPTH: f32 = 25.0; // milliseconds
+pub const MAX_STEREO_OFFSET: f32 = 20.0; // milliseconds
+pub const MAX_LINES: usize = 8;
+// панорама, Хаас и перекрестная маршрутизация рассчитаны на стерео
+pub const MAX_CHANNELS: usize = 2;
+
+// Самое короткое время линии в мс, которое она действительно дает на частоте samplerate:
+// петля не короче сэмпла, а насыщение (drive) добавляет задержку своих фильтров
+pub fn min_delay_time(samplerate: f32, drive: bool) -> f32 {
+    let samples = if drive {
+        saturation::min_delay()
+    } else {
+        delay_line::MIN_DELAY_SAMPLES
+    };
+    (samples / samplerate * 1e3).max(MIN_DELAY_TIME)
+}
+
+// Куда линия пишет в стерео. В моно все режимы совпадают с Normal
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Routing {
+    // каждый канал пишется в свой буфер
+    #[default]
+    Normal,
+    // вход (сумма каналов) попадает только в левый канал, обратная связь линии
+    // пишет левый выход в правый буфер и наоборот: L -> R -> L
+    PingPong,
+    // моно-вход в оба канала, стерео на выходе дают сдвиг Хааса и фаза LFO
+    Spread,
+}
+
+// Как матрица видит стереовход
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Stereo {
+    #[default]
+    LeftRight,
+    // L/R кодируются в середину (канал 0) и бока (канал 1) до матрицы
+    // и декодируются обратно после смешивания, бока умножаются на ширину
+    MidSide,
+}
+
+// С какими каналами M/S работает линия. В L/R и моно линия всегда работает с обоими
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MidSide {
+    #[default]
+    Both,
+    Mid,
+    Side,
+}
+
+// Параметры одной линии в единицах ручек: проценты, мс, Гц, дБ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineSettings {
+    pub level: f32,
+    pub delay: f32,
+    pub interpolation: Interpolation,
+    pub sync: Mode,
+    pub division: Division,
+    pub lfo_shape: Shape,
+    pub lfo_rate: f32,
+    pub lfo_depth: f32,
+    // сдвиг фазы LFO между каналами в градусах
+    pub lfo_stereo: f32,
+    pub lowpass: f32,
+    pub highpass: f32,
+    pub resonance: f32,
+    pub drive_curve: Curve,
+    pub drive: f32,
+    pub drive_bias: f32,
+    pub drive_trim: f32,
+    pub pan: f32,
+    pub stereo_offset: f32,
+    pub routing: Routing,
+    // посылы этой линии в другие линии меняют каналы местами
+    pub swap_sends: bool,
+    pub mid_side: MidSide,
+}
+
+impl Default for LineSettings {
+    fn default() -> Self {
+        Self {
+            level: 0.0,
+            delay: 500.0,
+            interpolation: Interpolation::Linear,
+            sync: Mode::Free,
+            division: Division::Quarter,
+            lfo_shape: Shape::Sine,
+            lfo_rate: 0.5,
+            lfo_depth: 0.0,
+            lfo_stereo: 90.0,
+            lowpass: 20000.0,
+            highpass: 20.0,
+            resonance: std::f32::consts::FRAC_1_SQRT_2,
+            drive_curve: Curve::Off,
+            drive: 0.0,
+            drive_bias: 0.0,
+            drive_trim: 0.0,
+            pan: 0.0,
+            stereo_offset: 0.0,
+            routing: Routing::Normal,
+            swap_sends: false,
+            mid_side: MidSide::Both,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineParams {
+    // посыл со входа на выход, %
+    pub dry: f32,
+    pub line_count: usize,
+    pub safety: Safety,
+    pub stereo_mode: Stereo,
+    // ширина в режиме M/S: усиление боков на выходе, %
+    pub width: f32,
+    // посылы со входа в линии, %
+    pub input_sends: [f32; MAX_LINES],
+    // посылы из линии в линию [из какой][в какую], диагональ - обратная связь, %
+    pub sends: [[f32; MAX_LINES]; MAX_LINES],
+    pub lines: [LineSettings; MAX_LINES],
+    pub transport: TransportInfo,
+    // высота линий A и B по MIDI-нотам
+    pub tuning: TuningSettings,
+}
+
+impl Default for EngineParams {
+    fn default() -> Self {
+        Self {
+            dry: 100.0,
+            line_count: 2,
+            safety: Safety::Off,
+            stereo_mode: Stereo::LeftRight,
+            width: 100.0,
+            input_sends: [100.0; MAX_LINES],
+            sends: [[0.0; MAX_LINES]; MAX_LINES],
+            lines: [LineSettings::default(); MAX_LINES],
+            transport: TransportInfo::default(),
+            tuning: TuningSettings::default(),
+        }
+    }
+}
+
+// Сглаживатели непрерывных параметров линии
+#[derive(Debug, Clone)]
+struct LineSmoothers {
+    level: Smoother,
+    delay: Smoother,
+    lfo_depth: Smoother,
+    lowpass: Smoother,
+    highpass: Smoother,
+    resonance: Smoother,
+    drive: Smoother,
+    drive_bias: Smoother,
+    drive_trim: Smoother,
+    pan: Smoother,
+    stereo_offset: Smoother,
+}
+
+impl Default for LineSmoothers {
+    fn default() -> Self {
+        Self {
+            level: send_smoother(),
+            delay: Smoother::new(SmoothingStyle::Linear(50.0)),
+            lfo_depth: Smoother::new(SmoothingStyle::Linear(20.0)),
+            lowpass: Smoother::new(SmoothingStyle::Logarithmic(20.0)),
+            highpass: Smoother::new(SmoothingStyle::Logarithmic(20.0)),
+            resonance: Smoother::new(SmoothingStyle::Linear(20.0)),
+            drive: Smoother::new(SmoothingStyle::Linear(20.0)),
+            drive_bias: Smoother::new(SmoothingStyle::Linear(20.0)),
+            drive_trim: Smoother::new(SmoothingStyle::Linear(20.0)),
+            pan: Smoother::new(SmoothingStyle::Linear(10.0)),
+            stereo_offset: Smoother::new(SmoothingStyle::Linear(50.0)),
+        }
+    }
+}
+
+impl LineSmoothers {
+    fn for_each(&mut self, mut f: impl FnMut(&mut Smoother)) {
+        [
+            &mut self.level,
+            &mut self.delay,
+            &mut self.lfo_depth,
+            &mut self.lowpass,
+            &mut self.highpass,
+            &mut self.resonance,
+            &mut self.drive,
+            &mut self.drive_bias,
+            &mut self.drive_trim,
+            &mut self.pan,
+            &mut self.stereo_offset,
+        ]
+        .into_iter()
+        .for_each(&mut f);
+    }
+}
+
+fn send_smoother() -> Smoother {
+    Smoother::new(SmoothingStyle::Linear(10.0))
+}
+
+// Что prepare_block запоминает для process_frame
+#[derive(Debug, Default, Clone, Copy)]
+struct BlockState {
+    len: usize,
+    channels_number: usize,
+    line_count: usize,
+    lfo_phase_offsets: [f32; MAX_LINES],
+    // Маршрутизация разложена заранее, чтобы в цикле не было ветвлений:
+    // вход линии - доли своего и соседнего канала, [линия][канал]
+    input_gains: [[(f32, f32); MAX_CHANNELS]; MAX_LINES],
+    // посыл берет выход соседнего канала, [в какую линию][из какой линии]
+    crossed_sends: [[bool; MAX_LINES]; MAX_LINES],
+    // 0 для каналов M/S, которые линия не обрабатывает, [линия][канал]
+    write_gains: [[f32; MAX_CHANNELS]; MAX_LINES],
+    mid_side: bool,
+    note_tuning: bool,
+    // линии A и B заменены голосами
+    polyphony: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct MatrixDelayEngine {
+    samplerate: f32,
+    max_block_size: usize,
+    // сколько линий обрабатывалось в прошлом блоке
+    active_lines: usize,
+    // спектральный радиус матрицы посылов в конце последнего блока
+    loop_gain: f32,
+    // выходы линий на последнем сэмпле, [канал][линия]
+    line_outputs: [[f32; MAX_LINES]; MAX_CHANNELS],
+    block: BlockState,
+
+    lines: Vec<delay_line::DelayLine>,
+    lfos: Vec<lfo::Lfo>,
+    filters: Vec<filter::FeedbackFilter>,
+    saturators: Vec<saturation::Saturator>,
+    limiters: Vec<stability::WriteLimiter>,
+    tuning: NoteTuning,
+    voices: VoicePool,
+
+    dry_smoother: Smoother,
+    width_smoother: Smoother,
+    in_send_smoothers: Vec<Smoother>,
+    send_smoothers: Vec<Vec<Smoother>>,
+    line_smoothers: Vec<LineSmoothers>,
+
+    dry_automation_samples: Vec<f32>,
+    width_automation_samples: Vec<f32>,
+    in_send_automation_samples: Vec<Vec<f32>>,
+    // [из какой линии][в какую линию][сэмпл]
+    send_automation_samples: Vec<Vec<Vec<f32>>>,
+    level_automation_samples: Vec<Vec<f32>>,
+    pan_automation_samples: Vec<Vec<f32>>,
+    stereo_offset_automation_samples: Vec<Vec<f32>>,
+}
+
+impl MatrixDelayEngine {
+    pub fn new(channels_number: usize, max_block_size: usize, samplerate: f32) -> Self {
+        let mut engine = Self::default();
+        engine.init(channels_number, max_block_size, samplerate);
+        engine
+    }
+
+    pub fn init(&mut self, channels_number: usize, max_block_size: usize, samplerate: f32) {
+        assert!(
+            channels_number <= MAX_CHANNELS,
+            "at most {MAX_CHANNELS} channels are supported"
+        );
+        self.samplerate = samplerate;
+        self.max_block_size = max_block_size;
+        let delay_buffer_size =
+            (samplerate * (MAX_DELAY_TIME + MAX_LFO_DEPTH + MAX_STEREO_OFFSET) / 1e3) as usize + 5;
+
+        // Буферы выделяются под все линии сразу: число линий меняется без переинициализации
+        self.lines = (0..MAX_LINES)
+            .map(|_| {
+                let mut line = delay_line::DelayLine::default();
+                line.init(
+                    delay_buffer_size,
+                    channels_number,
+                    max_block_size,
+                    samplerate,
+                );
+                line
+            })
+            .collect();
+        self.lfos = (0..MAX_LINES)
+            .map(|_| {
+                let mut lfo = lfo::Lfo::default();
+                lfo.init(channels_number, max_block_size, samplerate);
+                lfo
+            })
+            .collect();
+        self.filters = (0..MAX_LINES)
+            .map(|_| {
+                let mut filter = filter::FeedbackFilter::default();
+                filter.init(channels_number, max_block_size);
+                filter
+            })
+            .collect();
+        self.saturators = (0..MAX_LINES)
+            .map(|_| {
+                let mut saturator = saturation::Saturator::default();
+                saturator.init(channels_number, max_block_size);
+                saturator
+            })
+            .collect();
+        self.limiters = (0..MAX_LINES)
+            .map(|_| {
+                let mut limiter = stability::WriteLimiter::default();
+                limiter.init(channels_number, samplerate);
+                limiter
+            })
+            .collect();
+
+        self.tuning.init(samplerate);
+        self.voices
+            .init(channels_number, max_block_size, samplerate);
+
+        self.dry_smoother = send_smoother();
+        self.width_smoother = send_smoother();
+        self.in_send_smoothers = vec![send_smoother(); MAX_LINES];
+        self.send_smoothers = vec![vec![send_smoother(); MAX_LINES]; MAX_LINES];
+        self.line_smoothers = vec![LineSmoothers::default(); MAX_LINES];
+        self.for_each_smoother(|smoother| smoother.init(samplerate));
+
+        self.dry_automation_samples = vec![0.0; max_block_size];
+        self.width_automation_samples = vec![0.0; max_block_size];
+        self.in_send_automation_samples = vec![vec![0.0; max_block_size]; MAX_LINES];
+        self.send_automation_samples = vec![vec![vec![0.0; max_block_size]; MAX_LINES]; MAX_LINES];
+        self.level_automation_samples = vec![vec![0.0; max_block_size]; MAX_LINES];
+        self.pan_automation_samples = vec![vec![0.0; max_block_size]; MAX_LINES];
+        self.stereo_offset_automation_samples = vec![vec![0.0; max_block_size]; MAX_LINES];
+        self.active_lines = 0;
+        self.loop_gain = 0.0;
+    }
+
+    pub fn reset(&mut self) {
+        self.reset_lines(0..MAX_LINES);
+        self.tuning.reset();
+        self.voices.reset();
+        self.for_each_smoother(Smoother::reset);
+        self.line_outputs = Default::default();
+    }
+
+    fn reset_lines(&mut self, lines: std::ops::Range<usize>) {
+        self.lines[lines.clone()]
+            .iter_mut()
+            .for_each(|line| line.reset());
+        self.lfos[lines.clone()]
+            .iter_mut()
+            .for_each(|lfo| lfo.reset());
+        self.filters[lines.clone()]
+            .iter_mut()
+            .for_each(|filter| filter.reset());
+        self.saturators[lines.clone()]
+            .iter_mut()
+            .for_each(|saturator| saturator.reset());
+        self.limiters[lines]
+            .iter_mut()
+            .for_each(|limiter| limiter.reset());
+    }
+
+    pub fn samplerate(&self) -> f32 {
+        self.samplerate
+    }
+
+    pub fn max_block_size(&self) -> usize {
+        self.max_block_size
+    }
+
+    // Ноты применяются с того сэмпла, перед которым вызваны.
+    // Пока настройка по нотам выключена, они только запоминаются
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        // в полифонии ноту играет свой голос, а не линии A и B
+        if self.block.polyphony {
+            self.voices.note_on(note, velocity);
+        } else {
+            self.tuning.note_on(note, velocity);
+        }
+    }
+
+    pub fn note_off(&mut self, note: u8) {
+        self.tuning.note_off(note);
+        self.voices.note_off(note);
+    }
+
+    // Сколько голосов полифонии сейчас звучит
+    pub fn active_voices(&self) -> usize {
+        self.voices.active_count()
+    }
+
+    // Поправка настройки голоса ноты в полифонии, offset в центах или процентах
+    pub fn modulate_voice(&mut self, note: u8, target: VoiceTarget, offset: f32) {
+        self.voices.modulate(note, target, offset);
+    }
+
+    // Ноты, чьи голоса освободились или были украдены с прошлого вызова
+    pub fn ended_notes(&mut self) -> impl Iterator<Item = u8> + use<> {
+        self.voices.ended_notes()
+    }
+
+    // Спектральный радиус матрицы посылов: от единицы и выше петля не затухает
+    pub fn loop_gain(&self) -> f32 {
+        self.loop_gain
+    }
+
+    // Выход линии на последнем сэмпле до уровня и панорамы, в M/S - середина и бока
+    pub fn line_output(&self, line_idx: usize, channel_idx: usize) -> f32 {
+        self.line_outputs[channel_idx][line_idx]
+    }
+
+    // Линия задержки для просмотра буфера; в полифонии линии A и B молчат, звучат голоса
+    pub fn delay_line(&self, line_idx: usize) -> &delay_line::DelayLine {
+        &self.lines[line_idx]
+    }
+
+    fn for_each_smoother(&mut self, mut f: impl FnMut(&mut Smoother)) {
+        f(&mut self.dry_smoother);
+        f(&mut self.width_smoother);
+        self.in_send_smoothers.iter_mut().for_each(&mut f);
+        self.send_smoothers.iter_mut().flatten().for_each(&mut f);
+        self.line_smoothers
+            .iter_mut()
+            .for_each(|line| line.for_each(&mut f));
+    }
+
+    // Обрабатывает блок неперемежающихся каналов на месте. Длина блока не больше
+    // max_block_size из init, число каналов - как в init
+    pub fn process(&mut self, channels: &mut [&mut [f32]], params: &EngineParams) {
+        let block_len = channels.first().map_or(0, |samples| samples.len());
+        self.prepare_block(channels.len(), block_len, params);
+
+        let mut frame = [0.0; MAX_CHANNELS];
+        let frame = &mut frame[..channels.len()];
+        for sample_idx in 0..block_len {
+            for (value, samples) in frame.iter_mut().zip(channels.iter()) {
+                *value = samples[sample_idx];
+            }
+            self.process_frame(frame, sample_idx);
+            for (value, samples) in frame.iter().zip(channels.iter_mut()) {
+                samples[sample_idx] = *value;
+            }
+        }
+    }
+
+    // Автоматизация и настройки линий на весь блок. Потом process_frame для каждого
+    // сэмпла блока по порядку, например из Buffer::iter_samples
+    pub fn prepare_block(
+        &mut self,
+        channels_number: usize,
+        block_len: usize,
+        params: &EngineParams,
+    ) {
+        debug_assert!(block_len <= self.max_block_size);
+        debug_assert!(channels_number <= MAX_CHANNELS);
+        // панорама и сдвиг Хааса имеют смысл только для стерео
+        let is_stereo = channels_number == 2;
+        let mid_side = is_stereo && params.stereo_mode == Stereo::MidSide;
+        let line_count = params.line_count.clamp(1, MAX_LINES);
+
+        // включенные линии не должны играть то, что осталось в них с прошлого раза
+        if line_count > self.active_lines {
+            self.reset_lines(self.active_lines..line_count);
+            // и сглаживатели не должны тянуться от значений, когда линия была выключена
+            let active_lines = self.active_lines;
+            self.line_smoothers[active_lines..line_count]
+                .iter_mut()
+                .for_each(|line| line.for_each(Smoother::reset));
+            self.in_send_smoothers[active_lines..line_count]
+                .iter_mut()
+                .for_each(Smoother::reset);
+            self.send_smoothers
+                .iter_mut()
+                .enumerate()
+                .flat_map(|(from_idx, row)| {
+                    row.iter_mut()
+                        .enumerate()
+                        .filter(move |(to_idx, _)| from_idx.max(*to_idx) >= active_lines)
+                })
+                .for_each(|(_, smoother)| smoother.reset());
+        }
+        self.active_lines = line_count;
+
+        let mut lfo_phase_offsets = [0.0; MAX_LINES];
+
+        // заполнение автоматизации
+        {
+            let samplerate = self.samplerate;
+            let to_gain = |s: &mut f32| *s = utils::knob_gain(*s);
+            let to_samples = |s: &mut f32| *s = samplerate * *s / 1e3;
+
+            self.dry_smoother.set_target(params.dry);
+            self.dry_smoother
+                .next_block(&mut self.dry_automation_samples[..block_len]);
+            self.dry_automation_samples[..block_len]
+                .iter_mut()
+                .for_each(to_gain);
+            self.width_smoother.set_target(params.width.max(0.0));
+            self.width_smoother
+                .next_block(&mut self.width_automation_samples[..block_len]);
+            self.width_automation_samples[..block_len]
+                .iter_mut()
+                .for_each(to_gain);
+
+            for (line_idx, lfo_phase_offset) in
+                lfo_phase_offsets[..line_count].iter_mut().enumerate()
+            {
+                let settings = &params.lines[line_idx];
+                let smoothers = &mut self.line_smoothers[line_idx];
+
+                let line = &mut self.lines[line_idx];
+                line.set_interpolation(settings.interpolation);
+                // при синхронизации с темпом время задается хостом, иначе ручкой в мс
+                let delay_ms = tempo_sync::synced_delay_ms(
+                    settings.sync,
+                    settings.division,
+                    &params.transport,
+                )
+                .unwrap_or(settings.delay);
+                smoothers
+                    .delay
+                    .set_target(delay_ms.clamp(MIN_DELAY_TIME, MAX_DELAY_TIME));
+                smoothers
+                    .delay
+                    .next_block(&mut line.delay_automation_samples[..block_len]);
+                line.delay_automation_samples[..block_len]
+                    .iter_mut()
+                    .for_each(to_samples);
+
+                let lfo = &mut self.lfos[line_idx];
+                lfo.shape = settings.lfo_shape;
+                lfo.rate_automation_samples[..block_len].fill(settings.lfo_rate);
+                smoothers
+                    .lfo_depth
+                    .set_target(settings.lfo_depth.clamp(0.0, MAX_LFO_DEPTH));
+                smoothers
+                    .lfo_depth
+                    .next_block(&mut lfo.depth_automation_samples[..block_len]);
+                lfo.depth_automation_samples[..block_len]
+                    .iter_mut()
+                    .for_each(to_samples);
+                *lfo_phase_offset = settings.lfo_stereo / 360.0;
+
+                let filter = &mut self.filters[line_idx];
+                // логарифмическому сглаживанию нужны положительные частоты
+                smoothers.lowpass.set_target(settings.lowpass.max(1.0));
+                smoothers
+                    .lowpass
+                    .next_block(&mut filter.lowpass_automation_samples[..block_len]);
+                smoothers.highpass.set_target(settings.highpass.max(1.0));
+                smoothers
+                    .highpass
+                    .next_block(&mut filter.highpass_automation_samples[..block_len]);
+                smoothers.resonance.set_target(settings.resonance.max(0.1));
+                smoothers
+                    .resonance
+                    .next_block(&mut filter.resonance_automation_samples[..block_len]);
+                filter.prepare_block(block_len, samplerate);
+
+                let saturator = &mut self.saturators[line_idx];
+                saturator.set_curve(settings.drive_curve);
+                smoothers.drive.set_target(settings.drive);
+                smoothers
+                    .drive
+                    .next_block(&mut saturator.drive_automation_samples[..block_len]);
+                smoothers.drive_bias.set_target(settings.drive_bias);
+                smoothers
+                    .drive_bias
+                    .next_block(&mut saturator.bias_automation_samples[..block_len]);
+                smoothers.drive_trim.set_target(settings.drive_trim);
+                smoothers
+                    .drive_trim
+                    .next_block(&mut saturator.trim_automation_samples[..block_len]);
+                saturator.prepare_block(block_len);
+
+                smoothers.level.set_target(settings.level);
+                smoothers
+                    .level
+                    .next_block(&mut self.level_automation_samples[line_idx][..block_len]);
+                self.level_automation_samples[line_idx][..block_len]
+                    .iter_mut()
+                    .for_each(to_gain);
+                smoothers.pan.set_target(settings.pan);
+                smoothers
+                    .pan
+                    .next_block(&mut self.pan_automation_samples[line_idx][..block_len]);
+                self.pan_automation_samples[line_idx][..block_len]
+                    .iter_mut()
+                    .for_each(to_gain);
+                smoothers.stereo_offset.set_target(
+                    settings
+                        .stereo_offset
+                        .clamp(-MAX_STEREO_OFFSET, MAX_STEREO_OFFSET),
+                );
+                smoothers
+                    .stereo_offset
+                    .next_block(&mut self.stereo_offset_automation_samples[line_idx][..block_len]);
+                self.stereo_offset_automation_samples[line_idx][..block_len]
+                    .iter_mut()
+                    .for_each(to_samples);
+
+                let in_send_smoother = &mut self.in_send_smoothers[line_idx];
+                in_send_smoother.set_target(params.input_sends[line_idx]);
+                in_send_smoother
+                    .next_block(&mut self.in_send_automation_samples[line_idx][..block_len]);
+                self.in_send_automation_samples[line_idx][..block_len]
+                    .iter_mut()
+                    .for_each(to_gain);
+                for to_idx in 0..line_count {
+                    let send_automation_samples =
+                        &mut self.send_automation_samples[line_idx][to_idx][..block_len];
+                    let send_smoother = &mut self.send_smoothers[line_idx][to_idx];
+                    send_smoother.set_target(params.sends[line_idx][to_idx]);
+                    send_smoother.next_block(send_automation_samples);
+                    send_automation_samples.iter_mut().for_each(to_gain);
+                }
+            }
+        }
+
+        // устойчивость петли оценивается по посылам в конце блока
+        if block_len > 0 {
+            let mut loop_matrix = [[0.0; MAX_LINES]; MAX_LINES];
+            for (row, sends_from) in loop_matrix.iter_mut().zip(&self.send_automation_samples) {
+                for (gain, send) in row.iter_mut().zip(sends_from).take(line_count) {
+                    *gain = send[block_len - 1];
+                }
+            }
+            self.loop_gain = stability::spectral_radius(&loop_matrix, line_count);
+        }
+        self.limiters
+            .iter_mut()
+            .for_each(|limiter| limiter.mode = params.safety);
+        self.tuning.set_settings(params.tuning);
+        self.voices.set_settings(params.tuning);
+        let polyphony = self.voices.is_polyphonic();
+        // голоса и линии A и B подменяют друг друга, в линиях не должно остаться старого звука
+        if polyphony != self.block.polyphony {
+            self.reset_lines(0..2);
+        }
+        if polyphony {
+            // все голоса, и те, что начнутся посреди блока, берут коэффициенты линий A и B
+            for voice in self.voices.voices.iter_mut() {
+                for line_idx in 0..2 {
+                    voice.lines[line_idx].set_interpolation(params.lines[line_idx].interpolation);
+                    voice.filters[line_idx]
+                        .copy_automation_from(&self.filters[line_idx], block_len);
+                    voice.saturators[line_idx]
+                        .copy_automation_from(&self.saturators[line_idx], block_len);
+                    voice.limiters[line_idx].mode = params.safety;
+                }
+            }
+        }
+
+        self.block = BlockState {
+            len: block_len,
+            channels_number,
+            line_count,
+            lfo_phase_offsets,
+            input_gains: std::array::from_fn(|line_idx| {
+                // в моно все режимы совпадают с Normal
+                let routing = if is_stereo {
+                    params.lines[line_idx].routing
+                } else {
+                    Routing::Normal
+                };
+                std::array::from_fn(|channel_idx| match routing {
+                    Routing::Normal => (1.0, 0.0),
+                    Routing::PingPong if channel_idx == 0 => (0.5, 0.5),
+                    Routing::PingPong => (0.0, 0.0),
+                    Routing::Spread => (0.5, 0.5),
+                })
+            }),
+            crossed_sends: std::array::from_fn(|to_idx| {
+                std::array::from_fn(|from_idx| {
+                    let settings = &params.lines[from_idx];
+                    is_stereo
+                        && if from_idx == to_idx {
+                            settings.routing == Routing::PingPong
+                        } else {
+                            settings.swap_sends
+                        }
+                })
+            }),
+            write_gains: std::array::from_fn(|line_idx| {
+                let channels = if mid_side {
+                    params.lines[line_idx].mid_side
+                } else {
+                    MidSide::Both
+                };
+                let (mid, side) = match channels {
+                    MidSide::Both => (1.0, 1.0),
+                    MidSide::Mid => (1.0, 0.0),
+                    MidSide::Side => (0.0, 1.0),
+                };
+                [mid, side]
+            }),
+            mid_side,
+            note_tuning: params.tuning.enabled,
+            polyphony,
+        };
+    }
+
+    // Один сэмпл всех каналов. Каналы обрабатываются вместе: запись в линию
+    // может брать выходы других каналов того же сэмпла.
+    // Встраивается в цикл вызывающего, иначе на каждый сэмпл заново читаются все буферы
+    #[inline(always)]
+    pub fn process_frame(&mut self, frame: &mut [f32], sample_idx: usize) {
+        let BlockState {
+            len: block_len,
+            channels_number,
+            line_count,
+            mid_side,
+            note_tuning,
+            polyphony,
+            ..
+        } = self.block;
+        // таблицы по ссылке: копировать их на каждый сэмпл заметно дороже
+        let BlockState {
+            lfo_phase_offsets,
+            input_gains,
+            crossed_sends,
+            write_gains,
+            ..
+        } = &self.block;
+        debug_assert!(sample_idx < block_len);
+        debug_assert_eq!(frame.len(), channels_number);
+        let is_stereo = channels_number == 2;
+        // по нотам время линий A и B - период тона вместо ручки
+        let note_delays = if note_tuning {
+            self.tuning.next_delays()
+        } else {
+            None
+        };
+
+        // [канал][линия]
+        let mut line_outputs = [[0.0; MAX_LINES]; MAX_CHANNELS];
+        for line_idx in 0..line_count {
+            for (channel_idx, channel_outputs) in
+                line_outputs[..channels_number].iter_mut().enumerate()
+            {
+                // LFO только удлиняет задержку: от заданного времени до времени + глубина
+                let lfo = &mut self.lfos[line_idx];
+                let rate = lfo.rate_automation_samples[sample_idx];
+                let modulation =
+                    0.5 + 0.5 * lfo.next_value(channel_idx, rate, lfo_phase_offsets[line_idx]);
+                let modulation_depth = lfo.depth_automation_samples[sample_idx] * modulation;
+
+                // в M/S каналы линии - середина и бока, сдвиг Хааса между ними не имеет смысла
+                let stereo_offset = if is_stereo && !mid_side {
+                    utils::haas_offset(
+                        self.stereo_offset_automation_samples[line_idx][sample_idx],
+                        channel_idx,
+                    )
+                } else {
+                    0.0
+                };
+
+                // вместо линии A или B читаются голоса, дальше по матрице идет их сумма
+                if polyphony && line_idx < 2 {
+                    channel_outputs[line_idx] =
+                        self.voices
+                            .read(line_idx, channel_idx, modulation_depth + stereo_offset);
+                    continue;
+                }
+
+                let line = &mut self.lines[line_idx];
+                let delay = match (note_delays, line_idx) {
+                    (Some((delay_a, _)), 0) => delay_a,
+                    (Some((_, delay_b)), 1) => delay_b,
+                    _ => line.delay_automation_samples[sample_idx],
+                } + modulation_depth
+                    + stereo_offset;
+                // насыщение в записи задерживает сигнал, чтение забирает это время себе
+                let delay = delay - self.saturators[line_idx].latency(delay);
+                line.set_channel_delay(delay, channel_idx);
+                channel_outputs[line_idx] = line.read_value_from_channel(channel_idx);
+            }
+        }
+
+        let mut inputs = [0.0; MAX_CHANNELS];
+        inputs
+            .iter_mut()
+            .zip(frame.iter())
+            .for_each(|(input, sample)| *input = *sample);
+        // дальше канал 0 - середина, канал 1 - бока
+        if mid_side {
+            let (mid, side) = utils::encode_mid_side(inputs[0], inputs[1]);
+            inputs = [mid, side];
+        }
+        // импульс ноты идет в линии как вход, но не в прямой сигнал; в M/S - только в середину
+        let excitation_gains = if mid_side {
+            [1.0, 0.0]
+        } else {
+            [1.0; MAX_CHANNELS]
+        };
+        let mut line_inputs = inputs;
+        if note_tuning {
+            let excitation = self.tuning.next_excitation();
+            line_inputs
+                .iter_mut()
+                .zip(excitation_gains)
+                .for_each(|(input, gain)| *input += excitation * gain);
+        }
+        if polyphony {
+            self.voices.next_excitations();
+        }
+
+        for (channel_idx, sample) in frame.iter_mut().enumerate() {
+            // в моно соседний канал - он сам, его доля и перекрестные посылы нулевые
+            let other_idx = channels_number - 1 - channel_idx;
+            let (input, other_input) = (line_inputs[channel_idx], line_inputs[other_idx]);
+            let (outputs, other_outputs) = (&line_outputs[channel_idx], &line_outputs[other_idx]);
+
+            let mut wet_component = 0.0;
+            for to_idx in 0..line_count {
+                let (input_gain, other_input_gain) = input_gains[to_idx][channel_idx];
+                let input = input * input_gain + other_input * other_input_gain;
+
+                if polyphony && to_idx < 2 {
+                    let excitation_gain = input_gain * excitation_gains[channel_idx]
+                        + other_input_gain * excitation_gains[other_idx];
+                    // усиления записи и посыла линейны, поэтому сразу умножаются на все слагаемые
+                    let gain = write_gains[to_idx][channel_idx];
+                    let in_send = self.in_send_automation_samples[to_idx][sample_idx] * gain;
+                    let sends = std::array::from_fn(|from_idx| {
+                        let send = if from_idx < line_count {
+                            self.send_automation_samples[from_idx][to_idx][sample_idx]
+                        } else {
+                            0.0
+                        };
+                        (send * gain, crossed_sends[to_idx][from_idx])
+                    });
+                    // посылы линий C–H одни на все голоса и идут в них вместе со входом
+                    let shared_input = (2..line_count).fold(input * in_send, |acc, from_idx| {
+                        let output = if crossed_sends[to_idx][from_idx] {
+                            other_outputs[from_idx]
+                        } else {
+                            outputs[from_idx]
+                        };
+                        acc + output
+                            * self.send_automation_samples[from_idx][to_idx][sample_idx]
+                            * gain
+                    });
+                    self.voices.write(
+                        to_idx,
+                        (channel_idx, other_idx),
+                        shared_input,
+                        excitation_gain * in_send,
+                        sends,
+                        sample_idx,
+                    );
+                } else {
+                    // Умножаем входной сигнал на параметр посыла и добавляем посылы всех линий
+                    let value_to_write = outputs[..line_count]
+                        .iter()
+                        .zip(other_outputs)
+                        .zip(crossed_sends[to_idx])
+                        .zip(&self.send_automation_samples)
+                        .fold(
+                            input * self.in_send_automation_samples[to_idx][sample_idx],
+                            |acc, (((output, other_output), crossed), sends_from)| {
+                                let output = if crossed { other_output } else { output };
+                                acc + output * sends_from[to_idx][sample_idx]
+                            },
+                        );
+                    // линия, занятая только серединой или боками, в другой канал ничего не пишет
+                    let value_to_write = value_to_write * write_gains[to_idx][channel_idx];
+
+                    // Насыщение до фильтров: ФВЧ убирает постоянную составляющую от асимметрии
+                    let value_to_write =
+                        self.saturators[to_idx].process(value_to_write, channel_idx, sample_idx);
+                    // Фильтры стоят в записи, поэтому каждый повтор темнее/тоньше предыдущего
+                    let value_to_write =
+                        self.filters[to_idx].process(value_to_write, channel_idx, sample_idx);
+                    // ограничитель держит петлю в рамках, даже если она разгоняется
+                    let value_to_write = self.limiters[to_idx].process(value_to_write, channel_idx);
+                    self.lines[to_idx].write_value_to_channel(value_to_write, channel_idx);
+                }
+
+                // в M/S линии смешиваются после декодирования, там же панорама
+                if !mid_side {
+                    let pan_gain = if is_stereo {
+                        utils::pan_gain(
+                            self.pan_automation_samples[to_idx][sample_idx],
+                            channel_idx,
+                        )
+                    } else {
+                        1.0
+                    };
+                    wet_component += outputs[to_idx]
+                        * self.level_automation_samples[to_idx][sample_idx]
+                        * pan_gain;
+                }
+            }
+
+            // Смешивание
+            let dry_component = inputs[channel_idx] * self.dry_automation_samples[sample_idx];
+            *sample = dry_component + wet_component;
+
+            // сдвиг каретки
+            self.lines[..line_count]
+                .iter_mut()
+                .for_each(|line| line.move_arrow_over_channel(channel_idx));
+            if polyphony {
+                self.voices.move_arrows(channel_idx);
+            }
+        }
+        if polyphony {
+            self.voices.finish_frame();
+        }
+        self.line_outputs = line_outputs;
+
+        if mid_side {
+            let width = self.width_automation_samples[sample_idx];
+            let (mut left, mut right) = utils::decode_mid_side(frame[0], frame[1] * width);
+            // каждая линия возвращается в L/R отдельно, чтобы панорама двигала ее, а не середину с боками
+            let [mid_outputs, side_outputs, ..] = &line_outputs;
+            for (line_idx, (mid, side)) in mid_outputs[..line_count]
+                .iter()
+                .zip(side_outputs)
+                .enumerate()
+            {
+                let level = self.level_automation_samples[line_idx][sample_idx];
+                let (line_left, line_right) =
+                    utils::decode_mid_side(mid * level, side * level * width);
+                let pan = self.pan_automation_samples[line_idx][sample_idx];
+                left += line_left * utils::pan_gain(pan, 0);
+                right += line_right * utils::pan_gain(pan, 1);
+            }
+            frame[0] = left;
+            frame[1] = right;
+        }
+    }
+}
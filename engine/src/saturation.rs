@@ -0,0 +1,266 @@
+use crate::delay_line::MIN_DELAY_SAMPLES;
+use std::f64::consts::TAU;
+
+// Передискретизация нужна, чтобы гармоники от насыщения не заворачивались в слышимый диапазон
+pub const OVERSAMPLING: usize = 4;
+// Две ступени 2x на полуполосных БИХ-фильтрах: две ветки фазовращателей первого порядка
+// на пониженной частоте, как в hiir. Задержка всего несколько сэмплов, но фаза нелинейна,
+// поэтому время линии сокращается на фазовую задержку на основной частоте петли.
+// 1x <-> 2x: полоса пропускания до 0.4 частоты дискретизации, подавление 80 дБ
+const STAGE_A: [f32; 6] = [
+    0.060297392,
+    0.21597144,
+    0.4125907,
+    0.6043586,
+    0.7727156,
+    0.9238861,
+];
+// 2x <-> 4x: сигнал занимает меньше половины полосы, 85 дБ хватает четырех коэффициентов
+const STAGE_B: [f32; 4] = [0.06069013, 0.22801137, 0.47433767, 0.7955655];
+// выше этой частоты петли приближение фазовой задержки не нужно: такие линии уже на минимуме
+const MAX_LATENCY_FREQUENCY: f32 = 0.25;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    #[default]
+    Off,
+    Tanh,
+    Tube,
+    HardClip,
+    Cubic,
+}
+
+impl Curve {
+    fn shape(self, x: f32) -> f32 {
+        match self {
+            Curve::Off => x,
+            Curve::Tanh => x.tanh(),
+            // отрицательная полуволна ограничивается мягче и позже - появляются четные гармоники
+            Curve::Tube => {
+                if x >= 0.0 {
+                    x.tanh()
+                } else {
+                    2.0 * (0.5 * x).tanh()
+                }
+            }
+            Curve::HardClip => x.clamp(-1.0, 1.0),
+            Curve::Cubic => {
+                let x = x.clamp(-1.0, 1.0);
+                1.5 * x - 0.5 * x * x * x
+            }
+        }
+    }
+}
+
+// Фаза звена (a + z^-1) / (1 + a z^-1) на частоте theta радиан на сэмпл
+fn allpass_phase(a: f64, theta: f64) -> f64 {
+    -theta + 2.0 * (a * theta.sin()).atan2(1.0 + a * theta.cos())
+}
+
+// Фазовая задержка передискретизации вверх и вниз в сэмплах на частоте frequency
+// (доля исходной частоты дискретизации). В полосе пропускания ветки синфазны, и ступень
+// вверх и вниз - это все ее звенья подряд на пониженной частоте: задержку нечетной ветки
+// на сэмпл повышенной частоты возвращает децимация, выход которой - на втором сэмпле пары
+pub fn phase_delay(frequency: f64) -> f64 {
+    let frequency = frequency.max(1e-6);
+    let theta = TAU * frequency;
+    let phase: f64 = STAGE_A
+        .iter()
+        .map(|&a| allpass_phase(a as f64, theta))
+        .chain(
+            STAGE_B
+                .iter()
+                .map(|&a| allpass_phase(a as f64, theta / 2.0)),
+        )
+        .sum();
+    -phase / theta
+}
+
+// Фазовая задержка почти четна по частоте: t0 + c2 f^2 + c4 f^4 через точные значения
+// на нуле, на середине и на краю рабочего диапазона
+fn latency_fit() -> [f32; 3] {
+    let (f1, f2) = (
+        MAX_LATENCY_FREQUENCY as f64 / 2.0,
+        MAX_LATENCY_FREQUENCY as f64,
+    );
+    let t0 = phase_delay(0.0);
+    let (r1, r2) = (phase_delay(f1) - t0, phase_delay(f2) - t0);
+    let det = f1 * f1 * f2.powi(4) - f1.powi(4) * f2 * f2;
+    let c2 = (r1 * f2.powi(4) - r2 * f1.powi(4)) / det;
+    let c4 = (r2 * f1 * f1 - r1 * f2 * f2) / det;
+    [t0 as f32, c2 as f32, c4 as f32]
+}
+
+fn fitted_latency(fit: &[f32; 3], delay: f32) -> f32 {
+    let frequency = (1.0 / delay).min(MAX_LATENCY_FREQUENCY);
+    let f2 = frequency * frequency;
+    fit[0] + f2 * (fit[1] + fit[2] * f2)
+}
+
+// Самая короткая петля с насыщением в сэмплах: сэмпл линии плюс задержка фильтров
+pub fn min_delay() -> f32 {
+    let fit = latency_fit();
+    (0..8).fold(MIN_DELAY_SAMPLES + fit[0], |delay, _| {
+        MIN_DELAY_SAMPLES + fitted_latency(&fit, delay)
+    })
+}
+
+// Полуполосный фильтр: четные коэффициенты в одной ветке, нечетные в другой.
+// Для каждого звена - прошлые вход и выход
+#[derive(Debug, Clone, Copy)]
+struct HalfBand<const N: usize> {
+    coefs: [f32; N],
+    inputs: [f32; N],
+    outputs: [f32; N],
+}
+
+impl<const N: usize> HalfBand<N> {
+    fn new(coefs: [f32; N]) -> Self {
+        Self {
+            coefs,
+            inputs: [0.0; N],
+            outputs: [0.0; N],
+        }
+    }
+
+    #[inline]
+    fn allpass(&mut self, idx: usize, input: f32) -> f32 {
+        let output = self.coefs[idx] * (input - self.outputs[idx]) + self.inputs[idx];
+        self.inputs[idx] = input;
+        self.outputs[idx] = output;
+        output
+    }
+
+    // Ветки на одном входе дают два сэмпла повышенной частоты подряд
+    #[inline]
+    fn upsample(&mut self, input: f32) -> [f32; 2] {
+        let (mut even, mut odd) = (input, input);
+        for idx in (0..N).step_by(2) {
+            even = self.allpass(idx, even);
+            odd = self.allpass(idx + 1, odd);
+        }
+        [even, odd]
+    }
+
+    // Два сэмпла повышенной частоты, ранний первым, в один исходной
+    #[inline]
+    fn downsample(&mut self, input: [f32; 2]) -> f32 {
+        let (mut even, mut odd) = (input[1], input[0]);
+        for idx in (0..N).step_by(2) {
+            even = self.allpass(idx, even);
+            odd = self.allpass(idx + 1, odd);
+        }
+        0.5 * (even + odd)
+    }
+}
+
+// Состояние передискретизации одного канала
+#[derive(Debug, Clone, Copy)]
+struct Oversampler {
+    up_a: HalfBand<6>,
+    up_b: HalfBand<4>,
+    down_b: HalfBand<4>,
+    down_a: HalfBand<6>,
+}
+
+impl Default for Oversampler {
+    fn default() -> Self {
+        Self {
+            up_a: HalfBand::new(STAGE_A),
+            up_b: HalfBand::new(STAGE_B),
+            down_b: HalfBand::new(STAGE_B),
+            down_a: HalfBand::new(STAGE_A),
+        }
+    }
+}
+
+// Насыщение в записи линии: drive -> смещение -> кривая -> выходной уровень
+#[derive(Debug, Default)]
+pub struct Saturator {
+    pub curve: Curve,
+    oversamplers: Vec<Oversampler>,
+    // фазовая задержка фильтров как многочлен от частоты петли, см. latency_fit
+    latency_fit: [f32; 3],
+
+    // до prepare_block - дБ, после - коэффициенты усиления
+    pub drive_automation_samples: Vec<f32>,
+    pub bias_automation_samples: Vec<f32>,
+    pub trim_automation_samples: Vec<f32>,
+}
+
+impl Saturator {
+    pub fn init(&mut self, channels_number: usize, max_buffer_size: usize) {
+        self.oversamplers = vec![Default::default(); channels_number];
+        self.latency_fit = latency_fit();
+        self.drive_automation_samples = vec![0.0; max_buffer_size];
+        self.bias_automation_samples = vec![0.0; max_buffer_size];
+        self.trim_automation_samples = vec![0.0; max_buffer_size];
+    }
+
+    pub fn reset(&mut self) {
+        self.oversamplers.fill(Default::default());
+    }
+
+    // Задержка в сэмплах, которую насыщение вносит в линию длиной delay: фазовая задержка
+    // на основной частоте ее петли, чтобы и повтор, и настроенный резонатор остались на месте
+    pub fn latency(&self, delay: f32) -> f32 {
+        if self.curve == Curve::Off {
+            0.0
+        } else {
+            fitted_latency(&self.latency_fit, delay)
+        }
+    }
+
+    pub fn set_curve(&mut self, curve: Curve) {
+        // при выключенной кривой фильтры стоят, их старое состояние дало бы щелчок
+        if self.curve != curve {
+            self.curve = curve;
+            self.reset();
+        }
+    }
+
+    pub fn prepare_block(&mut self, block_len: usize) {
+        let to_gain = |db: &mut f32| *db = crate::utils::db_to_gain(*db);
+        self.drive_automation_samples[..block_len]
+            .iter_mut()
+            .for_each(to_gain);
+        self.trim_automation_samples[..block_len]
+            .iter_mut()
+            .for_each(to_gain);
+    }
+
+    // Кривая и усиления блока от другого насыщения после его prepare_block
+    pub fn copy_automation_from(&mut self, other: &Self, block_len: usize) {
+        self.set_curve(other.curve);
+        self.drive_automation_samples[..block_len]
+            .copy_from_slice(&other.drive_automation_samples[..block_len]);
+        self.bias_automation_samples[..block_len]
+            .copy_from_slice(&other.bias_automation_samples[..block_len]);
+        self.trim_automation_samples[..block_len]
+            .copy_from_slice(&other.trim_automation_samples[..block_len]);
+    }
+
+    pub fn process(&mut self, input: f32, channel_idx: usize, sample_idx: usize) -> f32 {
+        if self.curve == Curve::Off {
+            return input;
+        }
+        let drive = self.drive_automation_samples[sample_idx];
+        let bias = self.bias_automation_samples[sample_idx];
+        let trim = self.trim_automation_samples[sample_idx];
+        // смещение рабочей точки не должно само по себе давать постоянную составляющую
+        let bias_offset = self.curve.shape(bias);
+
+        let curve = self.curve;
+        let shape = |value: f32| curve.shape(value * drive + bias) - bias_offset;
+        let oversampler = &mut self.oversamplers[channel_idx];
+        let mut downsampled = [0.0; 2];
+        for (downsampled, upsampled) in downsampled.iter_mut().zip(oversampler.up_a.upsample(input))
+        {
+            let upsampled = oversampler.up_b.upsample(upsampled);
+            *downsampled = oversampler.down_b.downsample(upsampled.map(shape));
+        }
+        let output = oversampler.down_a.downsample(downsampled);
+
+        output * trim
+    }
+}
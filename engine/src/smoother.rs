@@ -0,0 +1,89 @@
+// Сглаживание параметров внутри движка: хост (или рендер) задает только целевые значения,
+// а движок плавно ведет к ним значение по сэмплам
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SmoothingStyle {
+    #[default]
+    None,
+    // время в мс
+    Linear(f32),
+    // для частот: равномерно по октавам, значения должны быть больше нуля
+    Logarithmic(f32),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Smoother {
+    style: SmoothingStyle,
+    samplerate: f32,
+    current: f32,
+    target: f32,
+    // прибавка (Linear) или множитель (Logarithmic) на сэмпл
+    step: f32,
+    steps_left: u32,
+    // после сброса первое значение берется сразу, без сглаживания
+    snap: bool,
+}
+
+impl Smoother {
+    pub fn new(style: SmoothingStyle) -> Self {
+        Self {
+            style,
+            snap: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn init(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.snap = true;
+        self.steps_left = 0;
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        let time_ms = match self.style {
+            SmoothingStyle::None => 0.0,
+            SmoothingStyle::Linear(time_ms) | SmoothingStyle::Logarithmic(time_ms) => time_ms,
+        };
+        let steps = (time_ms * 1e-3 * self.samplerate).round() as u32;
+
+        if self.snap || steps == 0 {
+            self.current = target;
+            self.target = target;
+            self.steps_left = 0;
+            self.snap = false;
+            return;
+        }
+        if target == self.target {
+            return;
+        }
+
+        self.target = target;
+        self.steps_left = steps;
+        self.step = match self.style {
+            SmoothingStyle::Logarithmic(_) => (target / self.current).powf(1.0 / steps as f32),
+            _ => (target - self.current) / steps as f32,
+        };
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> f32 {
+        if self.steps_left > 0 {
+            self.steps_left -= 1;
+            self.current = if self.steps_left == 0 {
+                self.target
+            } else if let SmoothingStyle::Logarithmic(_) = self.style {
+                self.current * self.step
+            } else {
+                self.current + self.step
+            };
+        }
+        self.current
+    }
+
+    pub fn next_block(&mut self, block_values: &mut [f32]) {
+        block_values.iter_mut().for_each(|v| *v = self.next());
+    }
+}
@@ -0,0 +1,120 @@
+// Порог, выше которого ограничитель начинает прижимать запись в линию
+pub const LIMITER_THRESHOLD: f32 = 1.0;
+// время отпускания ограничителя
+const LIMITER_RELEASE_MS: f32 = 50.0;
+// число возведений в квадрат: оценка берется по норме A^(2^SQUARINGS)
+const SQUARINGS: usize = 16;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Safety {
+    #[default]
+    Off,
+    SoftClip,
+    Limiter,
+}
+
+// Спектральный радиус матрицы петли: если он больше единицы, хотя бы одна собственная
+// "мода" сети растет с каждым проходом и петля идет вразнос.
+// Собственные числа несимметричной матрицы бывают комплексными, поэтому вместо степенного
+// метода используется формула Гельфанда: rho(A) = lim ||A^k||^(1/k).
+// Матрица возводится в квадрат с нормировкой, масштаб копится в логарифме.
+pub fn spectral_radius<const N: usize>(matrix: &[[f32; N]; N], size: usize) -> f32 {
+    let size = size.min(N);
+    let mut power = [[0.0f64; N]; N];
+    for (power_row, row) in power.iter_mut().zip(matrix).take(size) {
+        for (p, m) in power_row.iter_mut().zip(row).take(size) {
+            *p = *m as f64;
+        }
+    }
+
+    let mut log_norm = match normalize(&mut power, size) {
+        Some(norm) => norm.ln(),
+        // нулевая матрица: петли нет
+        None => return 0.0,
+    };
+    let mut exponent = 1.0;
+    for _ in 0..SQUARINGS {
+        power = square(&power, size);
+        log_norm *= 2.0;
+        exponent *= 2.0;
+        match normalize(&mut power, size) {
+            Some(norm) => log_norm += norm.ln(),
+            // нильпотентная матрица: все собственные числа нулевые
+            None => return 0.0,
+        }
+    }
+
+    (log_norm / exponent).exp() as f32
+}
+
+// Делит матрицу на ее норму и возвращает норму, если она не нулевая
+fn normalize<const N: usize>(matrix: &mut [[f64; N]; N], size: usize) -> Option<f64> {
+    let norm = max_row_sum(matrix, size);
+    if norm <= f64::MIN_POSITIVE {
+        return None;
+    }
+    matrix
+        .iter_mut()
+        .take(size)
+        .for_each(|row| row.iter_mut().take(size).for_each(|v| *v /= norm));
+    Some(norm)
+}
+
+fn square<const N: usize>(matrix: &[[f64; N]; N], size: usize) -> [[f64; N]; N] {
+    let mut result = [[0.0; N]; N];
+    for (result_row, row) in result.iter_mut().zip(matrix).take(size) {
+        for (k, a) in row.iter().enumerate().take(size) {
+            for (r, b) in result_row.iter_mut().zip(&matrix[k]).take(size) {
+                *r += a * b;
+            }
+        }
+    }
+    result
+}
+
+// Бесконечная норма (максимальная сумма модулей по строкам) - субмультипликативна
+fn max_row_sum<const N: usize>(matrix: &[[f64; N]; N], size: usize) -> f64 {
+    matrix
+        .iter()
+        .take(size)
+        .map(|row| row.iter().take(size).map(|v| v.abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+// Защита записи в линию от разноса: мягкое ограничение или пиковый лимитер
+#[derive(Debug, Default)]
+pub struct WriteLimiter {
+    pub mode: Safety,
+    // огибающая пикового лимитера для каждого канала
+    envelope: Vec<f32>,
+    release: f32,
+}
+
+impl WriteLimiter {
+    pub fn init(&mut self, channels_number: usize, samplerate: f32) {
+        self.envelope = vec![0.0; channels_number];
+        self.release = (-1.0 / (LIMITER_RELEASE_MS * 1e-3 * samplerate)).exp();
+    }
+
+    pub fn reset(&mut self) {
+        self.envelope.fill(0.0);
+    }
+
+    pub fn process(&mut self, input: f32, channel_idx: usize) -> f32 {
+        match self.mode {
+            Safety::Off => input,
+            // линейно около нуля и плавно упирается в порог
+            Safety::SoftClip => LIMITER_THRESHOLD * (input / LIMITER_THRESHOLD).tanh(),
+            Safety::Limiter => {
+                // мгновенная атака, экспоненциальное отпускание
+                let envelope = &mut self.envelope[channel_idx];
+                *envelope = input.abs().max(*envelope * self.release);
+                if *envelope > LIMITER_THRESHOLD {
+                    input * LIMITER_THRESHOLD / *envelope
+                } else {
+                    input
+                }
+            }
+        }
+    }
+}
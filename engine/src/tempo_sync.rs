@@ -0,0 +1,68 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Free,
+    Straight,
+    Dotted,
+    Triplet,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Division {
+    SixtyFourth,
+    ThirtySecond,
+    Sixteenth,
+    Eighth,
+    #[default]
+    Quarter,
+    Half,
+    Bar,
+    TwoBars,
+    FourBars,
+}
+
+// Темп и размер от хоста (или из командной строки при офлайн-рендере)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TransportInfo {
+    pub tempo: Option<f64>,
+    pub time_signature: Option<(i32, i32)>,
+}
+
+impl Division {
+    fn quarter_notes(self, quarters_per_bar: f64) -> f64 {
+        match self {
+            Division::SixtyFourth => 1.0 / 16.0,
+            Division::ThirtySecond => 1.0 / 8.0,
+            Division::Sixteenth => 1.0 / 4.0,
+            Division::Eighth => 1.0 / 2.0,
+            Division::Quarter => 1.0,
+            Division::Half => 2.0,
+            Division::Bar => quarters_per_bar,
+            Division::TwoBars => 2.0 * quarters_per_bar,
+            Division::FourBars => 4.0 * quarters_per_bar,
+        }
+    }
+}
+
+// Время задержки в миллисекундах или None, если синхронизация выключена
+// или хост не сообщил темп. Ограничение диапазона остается на вызывающей стороне.
+pub fn synced_delay_ms(mode: Mode, division: Division, transport: &TransportInfo) -> Option<f32> {
+    let multiplier = match mode {
+        Mode::Free => return None,
+        Mode::Straight => 1.0,
+        Mode::Dotted => 1.5,
+        Mode::Triplet => 2.0 / 3.0,
+    };
+    let tempo = transport.tempo.filter(|t| *t > 0.0)?;
+
+    // без сигнатуры размера считаем такт 4/4
+    let quarters_per_bar = match transport.time_signature {
+        Some((numerator, denominator)) if numerator > 0 && denominator > 0 => {
+            numerator as f64 * 4.0 / denominator as f64
+        }
+        _ => 4.0,
+    };
+
+    let quarter_ms = 60_000.0 / tempo;
+    Some((division.quarter_notes(quarters_per_bar) * multiplier * quarter_ms) as f32)
+}
@@ -0,0 +1,167 @@
+// Настройка линий по MIDI-нотам: линия A звучит на высоте ноты, линия B - на заданный
+// интервал от нее. Задержка равна периоду тона, поэтому линия с обратной связью
+// становится резонатором (Karplus-Strong, гребенчатый фильтр)
+use crate::matrix::MIN_DELAY_TIME;
+
+pub const MAX_FINE_TUNE: f32 = 100.0; // cents
+pub const MAX_INTERVAL: i32 = 24; // semitones
+pub const MAX_GLIDE: f32 = 2000.0; // milliseconds
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningSettings {
+    pub enabled: bool,
+    // подстройка в центах
+    pub fine_tune: f32,
+    // интервал линии B от ноты в полутонах
+    pub interval_b: f32,
+    // время портаменто в мс
+    pub glide: f32,
+    // громкость шумового импульса при нажатии, %
+    pub excitation: f32,
+    // 1 - линии A и B играют последнюю ноту, больше - у каждой ноты своя пара линий
+    pub voices: usize,
+}
+
+impl Default for TuningSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            fine_tune: 0.0,
+            interval_b: 0.0,
+            glide: 0.0,
+            excitation: 0.0,
+            voices: 1,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NoteTuning {
+    samplerate: f32,
+    settings: TuningSettings,
+    // удерживаемые ноты по порядку нажатия, звучит последняя
+    held_notes: Vec<u8>,
+    // высота в полутонах MIDI, дробная во время портаменто. None, пока не сыграна ни одна нота
+    pitch: Option<f32>,
+    glide_target: f32,
+    glide_step: f32,
+    glide_steps_left: u32,
+    // задержки A и B в сэмплах, пересчитываются только при смене высоты
+    delays: (f32, f32),
+    excitation_left: usize,
+    excitation_gain: f32,
+    random_state: u32,
+}
+
+impl NoteTuning {
+    pub fn init(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        // нот всего 128 и они не повторяются, так что в обработке память не выделяется
+        self.held_notes = Vec::with_capacity(128);
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.held_notes.clear();
+        self.pitch = None;
+        self.glide_steps_left = 0;
+        self.excitation_left = 0;
+        self.random_state = 0x9E37_79B9;
+    }
+
+    // Настройки на блок
+    pub fn set_settings(&mut self, settings: TuningSettings) {
+        let retune = settings.fine_tune != self.settings.fine_tune
+            || settings.interval_b != self.settings.interval_b;
+        self.settings = settings;
+        if retune {
+            self.update_delays();
+        }
+    }
+
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        self.held_notes.retain(|held| *held != note);
+        self.held_notes.push(note);
+        self.glide_to(note as f32);
+
+        // шум длиной в один период тона: у Karplus-Strong спектр импульса и задает тембр
+        let gain = self.settings.excitation / 100.0 * velocity;
+        if self.settings.enabled && gain > 0.0 {
+            let period = self.period_samples(note as f32 + self.settings.fine_tune / 100.0);
+            self.excitation_left = (period.ceil() as usize).max(1);
+            self.excitation_gain = gain;
+        }
+    }
+
+    // Легато: после отпускания звучащей ноты высота возвращается к предыдущей удерживаемой,
+    // после последней остается как есть, чтобы резонатор доигрывал
+    pub fn note_off(&mut self, note: u8) {
+        let was_sounding = self.held_notes.last() == Some(&note);
+        self.held_notes.retain(|held| *held != note);
+        if let (true, Some(&previous)) = (was_sounding, self.held_notes.last()) {
+            self.glide_to(previous as f32);
+        }
+    }
+
+    // Задержки линий A и B в сэмплах на следующий сэмпл, None до первой ноты
+    pub fn next_delays(&mut self) -> Option<(f32, f32)> {
+        let pitch = self.pitch?;
+        if self.glide_steps_left > 0 {
+            self.glide_steps_left -= 1;
+            // последний шаг попадает точно в ноту, без накопленной ошибки
+            self.pitch = Some(if self.glide_steps_left == 0 {
+                self.glide_target
+            } else {
+                pitch + self.glide_step
+            });
+            self.update_delays();
+        }
+        Some(self.delays)
+    }
+
+    // Следующий сэмпл шумового импульса, 0 после его окончания
+    pub fn next_excitation(&mut self) -> f32 {
+        if self.excitation_left == 0 {
+            return 0.0;
+        }
+        self.excitation_left -= 1;
+        crate::utils::white_noise(&mut self.random_state) * self.excitation_gain
+    }
+
+    fn glide_to(&mut self, target: f32) {
+        let steps = (self.settings.glide.max(0.0) * 1e-3 * self.samplerate).round() as u32;
+        match self.pitch {
+            Some(pitch) if steps > 0 => {
+                // равномерно в полутонах, то есть экспоненциально по частоте
+                self.glide_target = target;
+                self.glide_step = (target - pitch) / steps as f32;
+                self.glide_steps_left = steps;
+            }
+            _ => {
+                self.pitch = Some(target);
+                self.glide_steps_left = 0;
+                self.update_delays();
+            }
+        }
+    }
+
+    fn update_delays(&mut self) {
+        if let Some(pitch) = self.pitch {
+            let pitch = pitch + self.settings.fine_tune / 100.0;
+            self.delays = (
+                self.period_samples(pitch),
+                self.period_samples(pitch + self.settings.interval_b),
+            );
+        }
+    }
+
+    fn period_samples(&self, pitch: f32) -> f32 {
+        note_period(pitch, self.samplerate)
+    }
+}
+
+// Период тона в сэмплах для высоты в полутонах MIDI
+pub fn note_period(pitch: f32, samplerate: f32) -> f32 {
+    let frequency = 440.0 * 2f32.powf((pitch - 69.0) / 12.0);
+    (samplerate / frequency).max(MIN_DELAY_TIME * 1e-3 * samplerate)
+}
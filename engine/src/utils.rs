@@ -0,0 +1,78 @@
+// utils.rs
+#[inline]
+pub fn db_to_gain(db: f32) -> f32 {
+    if db <= -80.0 {
+        0.0
+    } else {
+        10f32.powf(db / 20.0)
+    }
+}
+
+#[inline]
+pub fn knob_gain(knob_val: f32) -> f32 {
+    knob_val / 100.0
+}
+
+#[inline]
+pub fn convex(a: f32, b: f32, ratio: f32) -> f32 {
+    (a - b) * ratio + b // a*ratio + b*(1 - ratio)
+}
+
+// Баланс: в центре оба канала с единичным усилением, крайнее положение глушит противоположный
+#[inline]
+pub fn pan_gain(pan: f32, channel_idx: usize) -> f32 {
+    match channel_idx {
+        0 => (1.0 - pan).min(1.0),
+        1 => (1.0 + pan).min(1.0),
+        _ => 1.0,
+    }
+}
+
+// Эффект Хааса: положительный сдвиг задерживает правый канал, отрицательный - левый
+#[inline]
+pub fn haas_offset(offset: f32, channel_idx: usize) -> f32 {
+    match channel_idx {
+        0 => (-offset).max(0.0),
+        1 => offset.max(0.0),
+        _ => 0.0,
+    }
+}
+
+// L/R -> M/S. Делим пополам, чтобы обратное преобразование было просто суммой и разностью
+#[inline]
+pub fn encode_mid_side(left: f32, right: f32) -> (f32, f32) {
+    ((left + right) * 0.5, (left - right) * 0.5)
+}
+
+#[inline]
+pub fn decode_mid_side(mid: f32, side: f32) -> (f32, f32) {
+    (mid + side, mid - side)
+}
+
+// xorshift32, равномерно в [-1, 1]. Состояние не должно быть нулевым
+#[inline]
+pub fn white_noise(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+// 4-точечный кубический сплайн Эрмита (Catmull-Rom) между x0 и x1
+#[inline]
+pub fn hermite(xm1: f32, x0: f32, x1: f32, x2: f32, ratio: f32) -> f32 {
+    let c1 = 0.5 * (x1 - xm1);
+    let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+    let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+    ((c3 * ratio + c2) * ratio + c1) * ratio + x0
+}
+
+// Интерполяция Лагранжа 3-го порядка по узлам -1, 0, 1, 2
+#[inline]
+pub fn lagrange(xm1: f32, x0: f32, x1: f32, x2: f32, ratio: f32) -> f32 {
+    let d_m1 = ratio + 1.0;
+    let d_1 = ratio - 1.0;
+    let d_2 = ratio - 2.0;
+    -xm1 * ratio * d_1 * d_2 / 6.0 + x0 * d_m1 * d_1 * d_2 / 2.0 - x1 * d_m1 * ratio * d_2 / 2.0
+        + x2 * d_m1 * ratio * d_1 / 6.0
+}
@@ -0,0 +1,531 @@
+// Полифонический режим: каждая нота получает свою пару линий A/B с настройками матрицы.
+// Голоса звучат независимо, после отпускания вход в них закрывается и они затухают по обратной связи.
+// Все голоса выделяются в init с короткими буферами, в обработке память не выделяется
+use crate::delay_line::DelayLine;
+use crate::filter::FeedbackFilter;
+use crate::matrix::{MAX_CHANNELS, MAX_LFO_DEPTH, MAX_STEREO_OFFSET};
+use crate::saturation::Saturator;
+use crate::stability::WriteLimiter;
+use crate::tuning::{TuningSettings, note_period};
+use crate::utils;
+use std::cmp::Reverse;
+
+pub const MAX_VOICES: usize = 8;
+// самый длинный период в буфере голоса, ноты ниже 4 Гц звучат на этой высоте
+pub const MAX_VOICE_PERIOD: f32 = 250.0; // milliseconds
+// отпущенный голос освобождается, когда запись в него тише -100 дБ дольше своей петли
+const SILENCE_THRESHOLD: f32 = 1e-5;
+// украденный звучащий голос затухает столько перед новой нотой, чтобы не щелкнуть
+const STEAL_FADE: f32 = 5.0; // milliseconds
+// за столько после отпускания закрывается вход голоса, иначе живой вход не дал бы ему затихнуть
+const RELEASE_FADE: f32 = 5.0; // milliseconds
+
+// Выходы линий A и B голосов за сэмпл, [голос][канал][линия]
+pub type VoiceOutputs = [[[f32; 2]; MAX_CHANNELS]; MAX_VOICES];
+
+// Настройки, которые хост может модулировать отдельно для каждой ноты (полифоническая модуляция CLAP)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceTarget {
+    FineTune,
+    Excitation,
+}
+
+// Поправки голоса к общим настройкам: центы и проценты
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct VoiceModulation {
+    fine_tune: f32,
+    excitation: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct Voice {
+    // None - голос свободен
+    pub note: Option<u8>,
+    pub held: bool,
+    // номер нажатия: при нехватке голосов крадется самый старый
+    order: u64,
+    // высота в полутонах MIDI, дробная во время портаменто
+    pitch: f32,
+    glide_step: f32,
+    glide_steps_left: u32,
+    // с какой высоты начнется портаменто ноты, которая ждет конца затухания украденного голоса
+    glide_from: Option<f32>,
+    // задержки линий A и B в сэмплах
+    pub delays: [f32; 2],
+    pub lines: [DelayLine; 2],
+    pub filters: [FeedbackFilter; 2],
+    pub saturators: [Saturator; 2],
+    pub limiters: [WriteLimiter; 2],
+    // пик записи за сэмпл и сколько сэмплов подряд он ниже порога тишины
+    peak: f32,
+    quiet_samples: usize,
+    excitation_left: usize,
+    excitation_gain: f32,
+    random_state: u32,
+    // сколько сэмплов еще затухает украденный голос и сила нажатия ноты, которая ждет конца
+    fade_left: usize,
+    velocity: f32,
+    // усиление входа и посылов остальных линий: 1 у нажатой ноты, к 0 после отпускания
+    input_gain: f32,
+    modulation: VoiceModulation,
+    // нота началась на текущем сэмпле: модуляция громкости импульса еще может его запустить
+    just_started: bool,
+}
+
+impl Voice {
+    pub fn is_active(&self) -> bool {
+        self.note.is_some()
+    }
+
+    // Значение, записанное в линию голоса: по нему видно, что голос затих
+    #[inline]
+    pub fn track(&mut self, value: f32) {
+        self.peak = self.peak.max(value.abs());
+    }
+
+    fn reset(&mut self) {
+        self.lines.iter_mut().for_each(DelayLine::reset);
+        self.filters.iter_mut().for_each(FeedbackFilter::reset);
+        self.saturators.iter_mut().for_each(Saturator::reset);
+        self.limiters.iter_mut().for_each(WriteLimiter::reset);
+        self.peak = 0.0;
+        self.quiet_samples = 0;
+        self.excitation_left = 0;
+        self.fade_left = 0;
+        self.glide_steps_left = 0;
+        self.input_gain = 1.0;
+    }
+
+    // Затих ли голос: тишина должна продержаться дольше петли, иначе в линии еще может быть звук
+    fn is_silent(&self, modulation_margin: f32) -> bool {
+        let loop_len = self.delays[0].max(self.delays[1]) + modulation_margin;
+        self.quiet_samples as f32 > loop_len
+    }
+
+    // Новая нота с чистыми линиями. С портаменто высота едет от glide_from к ноте
+    fn start(
+        &mut self,
+        note: u8,
+        velocity: f32,
+        glide_from: Option<f32>,
+        settings: &TuningSettings,
+        samplerate: f32,
+    ) {
+        self.reset();
+        self.note = Some(note);
+        self.velocity = velocity;
+        self.just_started = true;
+        let steps = (settings.glide.max(0.0) * 1e-3 * samplerate).round() as u32;
+        match glide_from {
+            Some(pitch) if steps > 0 && pitch != note as f32 => {
+                // равномерно в полутонах, как в одноголосом режиме
+                self.pitch = pitch;
+                self.glide_step = (note as f32 - pitch) / steps as f32;
+                self.glide_steps_left = steps;
+            }
+            _ => self.pitch = note as f32,
+        }
+        self.update_delays(settings, samplerate);
+        self.random_state = 0x9E37_79B9 ^ (note as u32 + 1).wrapping_mul(0x85EB_CA6B);
+        self.excite(velocity, settings);
+    }
+
+    fn update_delays(&mut self, settings: &TuningSettings, samplerate: f32) {
+        let pitch = self.pitch + self.modulation.fine_tune / 100.0;
+        self.delays = pitch_delays(settings, samplerate, pitch);
+    }
+
+    fn excitation_level(&self, velocity: f32, settings: &TuningSettings) -> f32 {
+        (settings.excitation + self.modulation.excitation).clamp(0.0, 100.0) / 100.0 * velocity
+    }
+
+    // шум длиной в период линии A, как в одноголосом режиме
+    fn excite(&mut self, velocity: f32, settings: &TuningSettings) {
+        let gain = self.excitation_level(velocity, settings);
+        if gain > 0.0 {
+            self.excitation_left = (self.delays[0].ceil() as usize).max(1);
+            self.excitation_gain = gain;
+        }
+    }
+
+    // Следующий шаг портаменто, последний попадает точно в ноту
+    fn glide(&mut self, settings: &TuningSettings, samplerate: f32) {
+        let Some(note) = self.note else {
+            return;
+        };
+        self.glide_steps_left -= 1;
+        self.pitch = if self.glide_steps_left == 0 {
+            note as f32
+        } else {
+            self.pitch + self.glide_step
+        };
+        self.update_delays(settings, samplerate);
+    }
+
+    fn next_excitation(&mut self) -> f32 {
+        if self.excitation_left == 0 {
+            return 0.0;
+        }
+        self.excitation_left -= 1;
+        utils::white_noise(&mut self.random_state) * self.excitation_gain
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VoicePool {
+    samplerate: f32,
+    settings: TuningSettings,
+    pub voices: Vec<Voice>,
+    // выходы и импульсы голосов на текущем сэмпле
+    pub outputs: VoiceOutputs,
+    pub excitations: [f32; MAX_VOICES],
+    next_order: u64,
+    // высота последней нажатой ноты, от нее начинается портаменто следующей
+    last_pitch: Option<f32>,
+    // запас к периоду на LFO и сдвиг Хааса, в сэмплах
+    modulation_margin: f32,
+    // затухание украденного голоса в сэмплах
+    steal_fade: usize,
+    // шаг усиления входа за сэмпл после отпускания
+    release_step: f32,
+    // ноты, чьи голоса закончились с прошлого ended_notes, по биту на ноту
+    ended_notes: u128,
+}
+
+impl VoicePool {
+    pub fn init(&mut self, channels_number: usize, max_block_size: usize, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.modulation_margin = samplerate * (MAX_LFO_DEPTH + MAX_STEREO_OFFSET) / 1e3;
+        self.steal_fade = ((samplerate * STEAL_FADE / 1e3) as usize).max(1);
+        self.release_step = 1.0 / (samplerate * RELEASE_FADE / 1e3).max(1.0);
+        let delay_buffer_size =
+            (samplerate * (MAX_VOICE_PERIOD + MAX_LFO_DEPTH + MAX_STEREO_OFFSET) / 1e3) as usize
+                + 5;
+
+        self.voices = (0..MAX_VOICES)
+            .map(|_| {
+                let mut voice = Voice::default();
+                voice.lines.iter_mut().for_each(|line| {
+                    line.init(
+                        delay_buffer_size,
+                        channels_number,
+                        max_block_size,
+                        samplerate,
+                    )
+                });
+                voice
+                    .filters
+                    .iter_mut()
+                    .for_each(|filter| filter.init(channels_number, max_block_size));
+                voice
+                    .saturators
+                    .iter_mut()
+                    .for_each(|saturator| saturator.init(channels_number, max_block_size));
+                voice
+                    .limiters
+                    .iter_mut()
+                    .for_each(|limiter| limiter.init(channels_number, samplerate));
+                voice
+            })
+            .collect();
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        let ended_notes = &mut self.ended_notes;
+        self.voices.iter_mut().for_each(|voice| {
+            end_note(voice, ended_notes);
+            voice.held = false;
+            voice.reset();
+        });
+        self.next_order = 0;
+        self.last_pitch = None;
+    }
+
+    // Полифония включена, когда включена настройка по нотам и голосов больше одного
+    pub fn is_polyphonic(&self) -> bool {
+        self.settings.enabled && self.voice_count() > 1
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.voices.iter().filter(|voice| voice.is_active()).count()
+    }
+
+    fn voice_count(&self) -> usize {
+        self.settings.voices.clamp(1, MAX_VOICES)
+    }
+
+    // Настройки на блок
+    pub fn set_settings(&mut self, settings: TuningSettings) {
+        let retune = settings.fine_tune != self.settings.fine_tune
+            || settings.interval_b != self.settings.interval_b;
+        self.settings = settings;
+
+        // голоса сверх заданного числа замолкают сразу, без полифонии - все
+        let voice_count = if self.is_polyphonic() {
+            self.voice_count()
+        } else {
+            0
+        };
+        let ended_notes = &mut self.ended_notes;
+        self.voices.iter_mut().skip(voice_count).for_each(|voice| {
+            end_note(voice, ended_notes);
+            voice.fade_left = 0;
+        });
+
+        if retune {
+            // затухающий после кражи голос держит высоту старой ноты, новая возьмет свою при старте
+            for voice in self
+                .voices
+                .iter_mut()
+                .filter(|voice| voice.is_active() && voice.fade_left == 0)
+            {
+                voice.update_delays(&settings, self.samplerate);
+            }
+        }
+    }
+
+    pub fn note_on(&mut self, note: u8, velocity: f32) {
+        let voice_count = self.voice_count();
+        let voices = &mut self.voices[..voice_count];
+        // Та же нота перезапускается в своем голосе, иначе берется свободный. Если свободных нет,
+        // крадется отпущенный, который дольше всех молчит, за ним самый старый удерживаемый
+        let voice_idx = voices
+            .iter()
+            .position(|voice| voice.note == Some(note))
+            .or_else(|| voices.iter().position(|voice| !voice.is_active()))
+            .unwrap_or_else(|| {
+                voices
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, voice)| {
+                        (voice.held, Reverse(voice.quiet_samples), voice.order)
+                    })
+                    .map_or(0, |(voice_idx, _)| voice_idx)
+            });
+
+        let glide_from = self.last_pitch.replace(note as f32);
+        let voice = &mut voices[voice_idx];
+        // у новой ноты своя модуляция, хост пришлет ее после нажатия
+        voice.modulation = VoiceModulation::default();
+        // украденная нота для хоста закончилась сразу, хотя еще затухает
+        if let Some(stolen) = voice.note
+            && stolen != note
+        {
+            self.ended_notes |= 1 << stolen;
+        }
+        if !voice.is_active() || voice.is_silent(self.modulation_margin) {
+            voice.start(note, velocity, glide_from, &self.settings, self.samplerate);
+        } else if voice.fade_left > 0 {
+            // голос еще затухает после кражи: начнется последняя нажатая нота
+            voice.note = Some(note);
+            voice.velocity = velocity;
+            voice.glide_from = glide_from;
+        } else if voice.note == Some(note) {
+            // повторная нота доигрывает поверх старой, как повторный щипок струны
+            voice.excite(velocity, &self.settings);
+        } else {
+            // сброс звучащего голоса - щелчок, поэтому старая нота сначала затухает
+            voice.note = Some(note);
+            voice.velocity = velocity;
+            voice.glide_from = glide_from;
+            voice.fade_left = self.steal_fade;
+            voice.excitation_left = 0;
+        }
+        voice.held = true;
+        voice.order = self.next_order;
+        self.next_order += 1;
+    }
+
+    // Отпущенный голос звучит дальше, пока его не погасит обратная связь. Вход в него
+    // закрывается за RELEASE_FADE
+    pub fn note_off(&mut self, note: u8) {
+        // без полифонии своего голоса у ноты нет, для хоста она заканчивается при отпускании
+        if !self.is_polyphonic() {
+            self.ended_notes |= 1 << note;
+        }
+        self.voices
+            .iter_mut()
+            .filter(|voice| voice.note == Some(note))
+            .for_each(|voice| voice.held = false);
+    }
+
+    // Поправка одной настройки голоса ноты, offset в центах или процентах.
+    // Пришедшая на первом сэмпле ноты громкость импульса еще успевает его запустить
+    pub fn modulate(&mut self, note: u8, target: VoiceTarget, offset: f32) {
+        let Some(voice) = self
+            .voices
+            .iter_mut()
+            .find(|voice| voice.note == Some(note))
+        else {
+            return;
+        };
+        match target {
+            VoiceTarget::FineTune => {
+                voice.modulation.fine_tune = offset;
+                // затухающий после кражи голос возьмет поправку при старте новой ноты
+                if voice.fade_left == 0 {
+                    voice.update_delays(&self.settings, self.samplerate);
+                }
+            }
+            VoiceTarget::Excitation => {
+                voice.modulation.excitation = offset;
+                if voice.just_started {
+                    voice.excite(voice.velocity, &self.settings);
+                } else if voice.excitation_left > 0 {
+                    voice.excitation_gain = voice.excitation_level(voice.velocity, &self.settings);
+                }
+            }
+        }
+    }
+
+    // Ноты, чьи голоса освободились или были украдены с прошлого вызова
+    pub fn ended_notes(&mut self) -> impl Iterator<Item = u8> + use<> {
+        let ended_notes = std::mem::take(&mut self.ended_notes);
+        (0..128).filter(move |note| ended_notes & (1 << note) != 0)
+    }
+
+    // Следующий сэмпл шумового импульса каждого голоса в excitations
+    pub fn next_excitations(&mut self) {
+        for (excitation, voice) in self.excitations.iter_mut().zip(self.voices.iter_mut()) {
+            *excitation = voice.next_excitation();
+        }
+    }
+
+    // Читает линию A или B канала у всех звучащих голосов в outputs и возвращает сумму.
+    // modulation - LFO и сдвиг Хааса линии в сэмплах
+    #[inline(never)]
+    pub fn read(&mut self, line_idx: usize, channel_idx: usize, modulation: f32) -> f32 {
+        let mut sum = 0.0;
+        for (voice, outputs) in self.voices.iter_mut().zip(self.outputs.iter_mut()) {
+            if !voice.is_active() {
+                continue;
+            }
+            // насыщение в записи задерживает сигнал, чтение забирает это время себе
+            let delay = voice.delays[line_idx] + modulation;
+            let delay = delay - voice.saturators[line_idx].latency(delay);
+            let line = &mut voice.lines[line_idx];
+            line.set_channel_delay(delay, channel_idx);
+            // украденный голос затухает и на выходе, и в своей петле
+            let fade = if voice.fade_left > 0 {
+                voice.fade_left as f32 / self.steal_fade as f32
+            } else {
+                1.0
+            };
+            let value = line.read_value_from_channel(channel_idx) * fade;
+            outputs[channel_idx][line_idx] = value;
+            sum += value;
+        }
+        sum
+    }
+
+    // Запись в линию A или B канала у всех звучащих голосов. Голос - отдельная пара A/B:
+    // sends - посылы из A и B с признаком перекрестного канала. input - вход вместе с посылами
+    // остальных линий, он общий для всех голосов и закрывается у отпущенных.
+    // Вход, импульс и посылы уже умножены на усиления записи
+    #[inline(never)]
+    pub fn write(
+        &mut self,
+        line_idx: usize,
+        (channel_idx, other_idx): (usize, usize),
+        input: f32,
+        excitation_gain: f32,
+        sends: [(f32, bool); 2],
+        sample_idx: usize,
+    ) {
+        for ((voice, outputs), excitation) in self
+            .voices
+            .iter_mut()
+            .zip(&self.outputs)
+            .zip(self.excitations)
+        {
+            if !voice.is_active() {
+                continue;
+            }
+            let value_to_write = outputs[channel_idx]
+                .iter()
+                .zip(outputs[other_idx])
+                .zip(sends)
+                .fold(
+                    input * voice.input_gain + excitation * excitation_gain,
+                    |acc, ((output, other_output), (send, crossed))| {
+                        let output = if crossed { other_output } else { *output };
+                        acc + output * send
+                    },
+                );
+            let value_to_write =
+                voice.saturators[line_idx].process(value_to_write, channel_idx, sample_idx);
+            let value_to_write =
+                voice.filters[line_idx].process(value_to_write, channel_idx, sample_idx);
+            let value_to_write = voice.limiters[line_idx].process(value_to_write, channel_idx);
+            voice.track(value_to_write);
+            voice.lines[line_idx].write_value_to_channel(value_to_write, channel_idx);
+        }
+    }
+
+    pub fn move_arrows(&mut self, channel_idx: usize) {
+        self.voices
+            .iter_mut()
+            .filter(|voice| voice.is_active())
+            .flat_map(|voice| voice.lines.iter_mut())
+            .for_each(|line| line.move_arrow_over_channel(channel_idx));
+    }
+
+    // Конец сэмпла: портаменто и вход голосов делают шаг, украденные голоса после затухания
+    // начинают новую ноту, отпущенные голоса, которые затихли, освобождаются
+    pub fn finish_frame(&mut self) {
+        for voice in self.voices.iter_mut().filter(|voice| voice.is_active()) {
+            if voice.peak < SILENCE_THRESHOLD {
+                voice.quiet_samples += 1;
+            } else {
+                voice.quiet_samples = 0;
+            }
+            voice.peak = 0.0;
+            voice.just_started = false;
+            voice.input_gain = if voice.held {
+                (voice.input_gain + self.release_step).min(1.0)
+            } else {
+                (voice.input_gain - self.release_step).max(0.0)
+            };
+
+            if voice.fade_left > 0 {
+                voice.fade_left -= 1;
+                if voice.fade_left == 0
+                    && let Some(note) = voice.note
+                {
+                    let glide_from = voice.glide_from.take();
+                    voice.start(
+                        note,
+                        voice.velocity,
+                        glide_from,
+                        &self.settings,
+                        self.samplerate,
+                    );
+                }
+            } else if !voice.held
+                && voice.excitation_left == 0
+                && voice.is_silent(self.modulation_margin)
+            {
+                end_note(voice, &mut self.ended_notes);
+            } else if voice.glide_steps_left > 0 {
+                voice.glide(&self.settings, self.samplerate);
+            }
+        }
+    }
+}
+
+// Задержки линий A и B для высоты в полутонах MIDI в сэмплах
+fn pitch_delays(settings: &TuningSettings, samplerate: f32, pitch: f32) -> [f32; 2] {
+    let pitch = pitch + settings.fine_tune / 100.0;
+    [
+        note_period(pitch, samplerate),
+        note_period(pitch + settings.interval_b, samplerate),
+    ]
+}
+
+// Голос замолкает, его нота попадает в ended_notes
+fn end_note(voice: &mut Voice, ended_notes: &mut u128) {
+    if let Some(note) = voice.note.take() {
+        *ended_notes |= 1 << note;
+    }
+}
@@ -0,0 +1,47 @@
+use microdelay_engine::analysis::{impulse_response, magnitude_response};
+use microdelay_engine::{EngineParams, MatrixDelayEngine};
+
+const SAMPLERATE: f32 = 48000.0;
+const LEN: usize = 4096;
+
+fn bin(frequency: f32) -> usize {
+    (frequency / SAMPLERATE * LEN as f32).round() as usize
+}
+
+#[test]
+fn delta_is_flat() {
+    let mut impulse = vec![0.0; LEN];
+    impulse[0] = 1.0;
+    assert!(
+        magnitude_response(&impulse)
+            .iter()
+            .all(|db| db.abs() < 1e-4)
+    );
+}
+
+#[test]
+fn dry_and_echo_make_comb() {
+    // прямой сигнал и одно эхо через 1 мс: пики через 1 кГц, провалы между ними
+    let mut params = EngineParams::default();
+    params.lines[0].level = 100.0;
+    params.lines[0].delay = 1.0;
+    let mut engine = MatrixDelayEngine::new(2, 256, SAMPLERATE);
+    let response = impulse_response(&mut engine, &params, LEN);
+    // фильтры по умолчанию выключены: ровно два отсчета
+    let mut expected = vec![0.0; LEN];
+    expected[0] = 1.0;
+    expected[48] = 1.0;
+    assert_eq!(response, expected);
+
+    let magnitude = magnitude_response(&response);
+    assert_eq!(magnitude.len(), LEN / 2 + 1);
+    for peak in [1000.0, 2000.0, 3000.0] {
+        assert!((magnitude[bin(peak)] - 6.02).abs() < 1e-2);
+    }
+    for notch in [500.0, 1500.0] {
+        assert!(magnitude[bin(notch)] < -30.0);
+    }
+
+    // движок сбрасывается, повторный расчет дает то же самое
+    assert_eq!(impulse_response(&mut engine, &params, LEN), response);
+}
@@ -0,0 +1,69 @@
+// Общие заготовки для интеграционных тестов. Каждый тест берет только часть из них
+#![allow(dead_code)]
+
+use microdelay_engine::delay_line::{DelayLine, Interpolation};
+use microdelay_engine::{EngineParams, MatrixDelayEngine};
+
+pub const SAMPLERATE: f32 = 48000.0;
+
+// Импульсная характеристика одной линии: импульс пишется на первом сэмпле
+pub fn delay_line_ir(interpolation: Interpolation, delay: f32, len: usize) -> Vec<f32> {
+    let mut line = DelayLine::default();
+    line.init(len + 8, 1, len, SAMPLERATE);
+    line.set_interpolation(interpolation);
+    line.set_delay(delay);
+
+    (0..len)
+        .map(|n| {
+            let value = line.read_value_from_channel(0);
+            line.write_value_to_channel(if n == 0 { 1.0 } else { 0.0 }, 0);
+            line.move_arrow_over_channel(0);
+            value
+        })
+        .collect()
+}
+
+// Моно-движок, блоками по block_size
+pub fn render_at(
+    params: &EngineParams,
+    input: &[f32],
+    block_size: usize,
+    samplerate: f32,
+) -> Vec<f32> {
+    let mut engine = MatrixDelayEngine::new(1, block_size, samplerate);
+    let mut output = input.to_vec();
+    for block in output.chunks_mut(block_size) {
+        engine.process(&mut [block], params);
+    }
+    output
+}
+
+pub fn render(params: &EngineParams, input: &[f32], block_size: usize) -> Vec<f32> {
+    render_at(params, input, block_size, SAMPLERATE)
+}
+
+pub fn impulse(len: usize) -> Vec<f32> {
+    let mut input = vec![0.0; len];
+    input[0] = 1.0;
+    input
+}
+
+// Одна линия A на выходе, без прямого сигнала
+pub fn single_echo(delay: f32) -> EngineParams {
+    let mut params = EngineParams {
+        dry: 0.0,
+        ..Default::default()
+    };
+    params.lines[0].level = 100.0;
+    params.lines[0].delay = delay;
+    params
+}
+
+pub fn peak_position(samples: &[f32]) -> usize {
+    samples
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        .map(|(idx, _)| idx)
+        .unwrap()
+}
@@ -0,0 +1,353 @@
+mod common;
+
+use common::{SAMPLERATE, impulse, peak_position, render, single_echo};
+use microdelay_engine::tempo_sync::{Division, Mode, TransportInfo};
+use microdelay_engine::{EngineParams, MatrixDelayEngine, MidSide, Routing, Stereo};
+
+#[test]
+fn echo_arrives_after_delay_time() {
+    let output = render(&single_echo(10.0), &impulse(2048), 256);
+    assert_eq!(peak_position(&output), 480);
+}
+
+#[test]
+fn default_filters_leave_clean_delay() {
+    // на краях диапазона фильтры в обходе: повторы без размытия, затухание ровно по посылу
+    let mut params = single_echo(10.0);
+    params.sends[0][0] = 50.0;
+    let output = render(&params, &impulse(4096), 256);
+    let mut expected = vec![0.0; 4096];
+    for echo in 1..=8 {
+        expected[echo * 480] = 0.5f32.powi(echo as i32 - 1);
+    }
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn permutation_network_visits_lines_in_turn() {
+    // восемь линий по кругу 0 -> 1 -> ... -> 7 -> 0, вход только в первую
+    let mut params = EngineParams {
+        dry: 0.0,
+        line_count: 8,
+        input_sends: [0.0; 8],
+        ..Default::default()
+    };
+    params.input_sends[0] = 100.0;
+    for line_idx in 0..8 {
+        params.lines[line_idx].level = 100.0;
+        params.lines[line_idx].delay = (line_idx + 1) as f32;
+        params.sends[line_idx][(line_idx + 1) % 8] = 50.0;
+    }
+
+    let len = 8192;
+    let mut expected = vec![0.0; len];
+    let (mut position, mut gain, mut line_idx) = (0, 1.0f32, 0);
+    loop {
+        position += 48 * (line_idx + 1);
+        if position >= len {
+            break;
+        }
+        expected[position] = gain;
+        gain *= 0.5;
+        line_idx = (line_idx + 1) % 8;
+    }
+    assert_eq!(render(&params, &impulse(len), 256), expected);
+}
+
+#[test]
+fn householder_network_decays_by_its_gain() {
+    // (I - 2/N) / 2 для N = 4: единичный вектор входа каждый круг умножается на -1/2
+    let mut params = EngineParams {
+        dry: 0.0,
+        line_count: 4,
+        ..Default::default()
+    };
+    for from_idx in 0..4 {
+        params.lines[from_idx].level = 100.0;
+        params.lines[from_idx].delay = 10.0;
+        for to_idx in 0..4 {
+            params.sends[from_idx][to_idx] = if from_idx == to_idx { 25.0 } else { -25.0 };
+        }
+    }
+
+    let output = render(&params, &impulse(4096), 256);
+    let mut expected = vec![0.0; 4096];
+    for echo in 1..=8 {
+        expected[echo * 480] = 4.0 * (-0.5f32).powi(echo as i32 - 1);
+    }
+    assert_eq!(output, expected);
+}
+
+#[test]
+fn line_output_is_taken_before_level() {
+    let mut params = single_echo(10.0);
+    params.lines[0].level = 0.0;
+    let mut engine = MatrixDelayEngine::new(1, 1024, SAMPLERATE);
+    engine.prepare_block(1, 1024, &params);
+    let mut line_output = vec![0.0; 1024];
+    for (sample_idx, value) in impulse(1024).into_iter().enumerate() {
+        let mut frame = [value];
+        engine.process_frame(&mut frame, sample_idx);
+        assert_eq!(frame[0], 0.0);
+        line_output[sample_idx] = engine.line_output(0, 0);
+    }
+    assert_eq!(peak_position(&line_output), 480);
+}
+
+#[test]
+fn dry_signal_passes_unchanged() {
+    let params = EngineParams {
+        input_sends: [0.0; 8],
+        ..Default::default()
+    };
+    let input: Vec<f32> = (0..1024).map(|n| (n as f32 * 0.01).sin()).collect();
+    assert_eq!(render(&params, &input, 256), input);
+}
+
+#[test]
+fn block_size_does_not_change_output() {
+    let mut params = single_echo(7.3);
+    params.sends[0][0] = 70.0;
+    params.lines[0].lfo_depth = 2.0;
+    params.lines[0].lowpass = 5000.0;
+
+    let input = impulse(8192);
+    let reference = render(&params, &input, 512);
+    for block_size in [1, 64, 100] {
+        let output = render(&params, &input, block_size);
+        for (n, (a, b)) in output.iter().zip(&reference).enumerate() {
+            assert!(
+                (a - b).abs() < 1e-5,
+                "block {block_size}, sample {n}: {a} != {b}"
+            );
+        }
+    }
+}
+
+#[test]
+fn synced_delay_follows_tempo() {
+    let mut params = single_echo(500.0);
+    params.lines[0].sync = Mode::Straight;
+    params.lines[0].division = Division::Sixteenth;
+    params.transport = TransportInfo {
+        tempo: Some(150.0),
+        time_signature: Some((4, 4)),
+    };
+
+    // шестнадцатая при 150 BPM - 100 мс
+    let output = render(&params, &impulse(8192), 512);
+    assert_eq!(peak_position(&output), 4800);
+}
+
+fn render_stereo(params: &EngineParams, left: &[f32], right: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let mut engine = MatrixDelayEngine::new(2, 256, SAMPLERATE);
+    let (mut left, mut right) = (left.to_vec(), right.to_vec());
+    for (left, right) in left.chunks_mut(256).zip(right.chunks_mut(256)) {
+        engine.process(&mut [left, right], params);
+    }
+    (left, right)
+}
+
+fn channel_energy(samples: &[f32]) -> f32 {
+    samples.iter().map(|value| value * value).sum()
+}
+
+#[test]
+fn pan_follows_balance_law() {
+    // в центре оба канала целиком, к краю противоположный канал затихает линейно
+    for (pan, left_gain, right_gain) in [
+        (0.0, 1.0, 1.0),
+        (-50.0, 1.0, 0.5),
+        (25.0, 0.75, 1.0),
+        (-100.0, 1.0, 0.0),
+        (100.0, 0.0, 1.0),
+    ] {
+        let mut params = single_echo(10.0);
+        params.lines[0].pan = pan;
+        let (left, right) = render_stereo(&params, &impulse(1024), &impulse(1024));
+        assert_eq!(
+            (left[480], right[480]),
+            (left_gain, right_gain),
+            "pan {pan}"
+        );
+    }
+}
+
+#[test]
+fn haas_offset_delays_one_channel() {
+    // 2 мс - 96 отсчетов; положительный сдвиг задерживает правый канал, отрицательный - левый
+    for (offset, left_tap, right_tap) in [(2.0, 480, 576), (-2.0, 576, 480), (0.0, 480, 480)] {
+        let mut params = single_echo(10.0);
+        params.lines[0].stereo_offset = offset;
+        let (left, right) = render_stereo(&params, &impulse(1024), &impulse(1024));
+        let mut expected_left = vec![0.0; 1024];
+        expected_left[left_tap] = 1.0;
+        let mut expected_right = vec![0.0; 1024];
+        expected_right[right_tap] = 1.0;
+        assert_eq!(
+            (left, right),
+            (expected_left, expected_right),
+            "offset {offset}"
+        );
+    }
+}
+#[test]
+fn ping_pong_alternates_channels() {
+    let mut params = single_echo(10.0);
+    params.sends[0][0] = 80.0;
+    params.lines[0].routing = Routing::PingPong;
+
+    // вход только справа: сумма каналов все равно уходит в левый
+    let (left, right) = render_stereo(&params, &vec![0.0; 2048], &impulse(2048));
+    let mut expected = (vec![0.0; 2048], vec![0.0; 2048]);
+    for echo in 1..=4 {
+        // первый повтор - половина суммы каналов, дальше каждый раз * 0.8 в другом канале
+        let value = 0.5 * 0.8f32.powi(echo as i32 - 1);
+        if echo % 2 == 1 {
+            expected.0[echo * 480] = value;
+        } else {
+            expected.1[echo * 480] = value;
+        }
+    }
+    assert_eq!((left, right), expected);
+}
+
+#[test]
+fn spread_feeds_both_channels() {
+    let mut params = single_echo(10.0);
+    params.lines[0].routing = Routing::Spread;
+
+    let (left, right) = render_stereo(&params, &impulse(1024), &vec![0.0; 1024]);
+    assert_eq!(peak_position(&left), 480);
+    assert_eq!(left, right);
+}
+
+#[test]
+fn swapped_sends_cross_channels() {
+    // A слышна только через посыл в B
+    let mut params = single_echo(10.0);
+    params.lines[0].level = 0.0;
+    params.input_sends[1] = 0.0;
+    params.sends[0][1] = 100.0;
+    params.lines[1].level = 100.0;
+    params.lines[1].delay = 10.0;
+
+    let input = impulse(2048);
+    let silence = vec![0.0; 2048];
+    let (left, right) = render_stereo(&params, &input, &silence);
+    assert!(channel_energy(&right) < 1e-3 * channel_energy(&left));
+
+    params.lines[0].swap_sends = true;
+    let (left, right) = render_stereo(&params, &input, &silence);
+    assert!(left.iter().all(|&value| value == 0.0));
+    assert_eq!(peak_position(&right), 960);
+    assert_eq!(right[960], 1.0);
+}
+
+#[test]
+fn frames_match_slices() {
+    let mut params = single_echo(7.3);
+    params.sends[0][0] = 60.0;
+    params.sends[0][1] = -40.0;
+    params.lines[0].routing = Routing::PingPong;
+    params.lines[0].lfo_depth = 2.0;
+    params.lines[1].level = 80.0;
+    params.lines[1].delay = 3.1;
+    params.lines[1].swap_sends = true;
+    params.lines[1].stereo_offset = 1.5;
+
+    let left: Vec<f32> = (0..4096).map(|n| (n as f32 * 0.013).sin()).collect();
+    let right: Vec<f32> = (0..4096).map(|n| (n as f32 * 0.007).cos()).collect();
+    let reference = render_stereo(&params, &left, &right);
+
+    let mut engine = MatrixDelayEngine::new(2, 256, SAMPLERATE);
+    let (mut frame_left, mut frame_right) = (left.clone(), right.clone());
+    for (left, right) in frame_left.chunks_mut(256).zip(frame_right.chunks_mut(256)) {
+        engine.prepare_block(2, left.len(), &params);
+        for (sample_idx, (left, right)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            let mut frame = [*left, *right];
+            engine.process_frame(&mut frame, sample_idx);
+            [*left, *right] = frame;
+        }
+    }
+    assert_eq!((frame_left, frame_right), reference);
+}
+
+fn mid_side(params: &mut EngineParams, width: f32) {
+    params.stereo_mode = Stereo::MidSide;
+    params.width = width;
+}
+
+#[test]
+fn mid_side_is_transparent_at_full_width() {
+    let mut params = EngineParams {
+        input_sends: [0.0; 8],
+        ..Default::default()
+    };
+    mid_side(&mut params, 100.0);
+    let left: Vec<f32> = (0..1024).map(|n| (n as f32 * 0.01).sin()).collect();
+    let right: Vec<f32> = (0..1024).map(|n| (n as f32 * 0.03).cos()).collect();
+
+    let (out_left, out_right) = render_stereo(&params, &left, &right);
+    for (output, input) in out_left
+        .iter()
+        .chain(&out_right)
+        .zip(left.iter().chain(&right))
+    {
+        assert!((output - input).abs() < 1e-6, "{output} != {input}");
+    }
+}
+
+#[test]
+fn zero_width_collapses_to_mid() {
+    let mut params = EngineParams {
+        input_sends: [0.0; 8],
+        ..Default::default()
+    };
+    mid_side(&mut params, 0.0);
+    let left: Vec<f32> = (0..2048).map(|n| (n as f32 * 0.01).sin()).collect();
+    let right = vec![0.25; 2048];
+
+    // после сглаживания ширины
+    let (out_left, out_right) = render_stereo(&params, &left, &right);
+    for n in 1024..2048 {
+        let mid = (left[n] + right[n]) * 0.5;
+        assert!((out_left[n] - mid).abs() < 1e-6 && (out_right[n] - mid).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn mid_line_ignores_side() {
+    let mut params = single_echo(10.0);
+    params.input_sends[1] = 0.0;
+    params.lines[0].mid_side = MidSide::Mid;
+    mid_side(&mut params, 100.0);
+
+    // чистые бока: линия середины молчит
+    let side: Vec<f32> = impulse(2048).iter().map(|value| -value).collect();
+    let (left, right) = render_stereo(&params, &impulse(2048), &side);
+    assert!(channel_energy(&left) < 1e-12 && channel_energy(&right) < 1e-12);
+
+    // чистая середина: повтор одинаковый в обоих каналах
+    let (left, right) = render_stereo(&params, &impulse(2048), &impulse(2048));
+    assert_eq!(peak_position(&left), 480);
+    assert_eq!(left, right);
+}
+
+#[test]
+fn mid_side_pans_decoded_lines() {
+    let mut params = single_echo(10.0);
+    params.input_sends[1] = 0.0;
+    params.lines[0].pan = 50.0;
+    // сдвиг Хааса в M/S не действует
+    params.lines[0].stereo_offset = 2.0;
+    mid_side(&mut params, 100.0);
+
+    // середина 0.75, бока 0.25: линия возвращает 1 слева и 0.5 справа, панорама вправо
+    // глушит левый канал вдвое
+    let right_input: Vec<f32> = impulse(1024).iter().map(|value| value * 0.5).collect();
+    let (left, right) = render_stereo(&params, &impulse(1024), &right_input);
+    let mut expected = vec![0.0; 1024];
+    expected[480] = 0.5;
+    assert_eq!((left, right), (expected.clone(), expected));
+}
@@ -0,0 +1,52 @@
+use microdelay_engine::filter::{FeedbackFilter, MAX_FILTER_CUTOFF, MIN_FILTER_CUTOFF};
+
+const SAMPLERATE: f32 = 48000.0;
+const LEN: usize = 256;
+
+// Импульсная характеристика фильтра при постоянных срезах
+fn impulse_response(lowpass: f32, highpass: f32) -> Vec<f32> {
+    let mut filter = FeedbackFilter::default();
+    filter.init(1, LEN);
+    filter.lowpass_automation_samples.fill(lowpass);
+    filter.highpass_automation_samples.fill(highpass);
+    filter.resonance_automation_samples.fill(0.707);
+    filter.prepare_block(LEN, SAMPLERATE);
+    (0..LEN)
+        .map(|sample_idx| filter.process((sample_idx == 0) as u8 as f32, 0, sample_idx))
+        .collect()
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum()
+}
+
+#[test]
+fn range_ends_bypass_filters() {
+    let mut expected = vec![0.0; LEN];
+    expected[0] = 1.0;
+    assert_eq!(
+        impulse_response(MAX_FILTER_CUTOFF, MIN_FILTER_CUTOFF),
+        expected
+    );
+}
+
+#[test]
+fn filters_fade_into_bypass_towards_range_ends() {
+    let bypass = impulse_response(MAX_FILTER_CUTOFF, MIN_FILTER_CUTOFF);
+    // чем ближе срез к краю, тем ближе характеристика к обходу
+    let lowpass_distances: Vec<f32> = [1000.0, 12000.0, 16000.0, 19000.0]
+        .into_iter()
+        .map(|cutoff| distance(&impulse_response(cutoff, MIN_FILTER_CUTOFF), &bypass))
+        .collect();
+    let highpass_distances: Vec<f32> = [1000.0, 30.0, 25.0, 21.0]
+        .into_iter()
+        .map(|cutoff| distance(&impulse_response(MAX_FILTER_CUTOFF, cutoff), &bypass))
+        .collect();
+    for distances in [lowpass_distances, highpass_distances] {
+        assert!(
+            distances.windows(2).all(|pair| pair[1] < pair[0]),
+            "{distances:?}"
+        );
+        assert!(distances[0] > 0.1, "{distances:?}");
+    }
+}
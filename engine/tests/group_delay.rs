@@ -0,0 +1,99 @@
+mod common;
+
+use microdelay_engine::delay_line::{Interpolation, MIN_DELAY_SAMPLES};
+use microdelay_engine::{MIN_DELAY_TIME, min_delay_time};
+
+const IR_LEN: usize = 512;
+const MODES: [Interpolation; 4] = [
+    Interpolation::Linear,
+    Interpolation::Hermite,
+    Interpolation::Lagrange,
+    Interpolation::Allpass,
+];
+
+fn impulse_response(interpolation: Interpolation, delay: f32) -> Vec<f64> {
+    common::delay_line_ir(interpolation, delay, IR_LEN)
+        .into_iter()
+        .map(f64::from)
+        .collect()
+}
+
+// Групповая задержка на нулевой частоте: центр масс импульсной характеристики
+fn dc_group_delay(ir: &[f64]) -> f64 {
+    let gain: f64 = ir.iter().sum();
+    let moment: f64 = ir.iter().enumerate().map(|(n, h)| n as f64 * h).sum();
+    moment / gain
+}
+
+fn assert_group_delay(interpolation: Interpolation, delay: f32) {
+    let measured = dc_group_delay(&impulse_response(interpolation, delay));
+    assert!(
+        (measured - delay as f64).abs() < 1e-3,
+        "{interpolation:?}: delay {delay} measured as {measured}"
+    );
+}
+
+#[test]
+fn fractional_sweep_matches_requested_delay() {
+    for interpolation in MODES {
+        for step in 0..=400 {
+            assert_group_delay(interpolation, 1.0 + step as f32 * 0.0625);
+        }
+    }
+}
+
+#[test]
+fn integer_delays_are_read_exactly() {
+    for interpolation in MODES {
+        for delay in 1..64 {
+            let ir = impulse_response(interpolation, delay as f32);
+            let peak = ir
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+                .unwrap();
+            assert_eq!(peak.0, delay, "{interpolation:?}");
+            assert!((peak.1 - 1.0).abs() < 1e-6, "{interpolation:?}");
+        }
+    }
+}
+
+#[test]
+fn delay_is_continuous_across_integer_boundaries() {
+    for interpolation in MODES {
+        for boundary in 2..16 {
+            for offset in [-1e-3_f32, 0.0, 1e-3] {
+                assert_group_delay(interpolation, boundary as f32 + offset);
+            }
+        }
+    }
+}
+
+#[test]
+fn minimum_delay_time_is_honoured() {
+    for samplerate in [44100.0_f32, 48000.0, 88200.0, 96000.0, 192000.0] {
+        let delay = samplerate * MIN_DELAY_TIME / 1e3;
+        for interpolation in MODES {
+            assert_group_delay(interpolation, delay);
+        }
+    }
+}
+
+#[test]
+fn minimum_delay_is_clamped_to_a_sample() {
+    // ниже 40 кГц 0.025 мс короче сэмпла: линия дает сэмпл, и это минимум, который видит редактор
+    for samplerate in [22050.0_f32, 32000.0] {
+        let delay = samplerate * MIN_DELAY_TIME / 1e3;
+        assert!(delay < MIN_DELAY_SAMPLES);
+        for interpolation in MODES {
+            let measured = dc_group_delay(&impulse_response(interpolation, delay));
+            assert!(
+                (measured - MIN_DELAY_SAMPLES as f64).abs() < 1e-3,
+                "{interpolation:?}: {samplerate} Hz measured as {measured}"
+            );
+        }
+        let min_time = min_delay_time(samplerate, false);
+        assert!((min_time - MIN_DELAY_SAMPLES / samplerate * 1e3).abs() < 1e-6);
+    }
+    assert_eq!(min_delay_time(48000.0, false), MIN_DELAY_TIME);
+}
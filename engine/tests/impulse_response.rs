@@ -0,0 +1,216 @@
+// Импульсные характеристики линии и всей матрицы на разных частотах дискретизации.
+// Эталоны лежат в tests/golden, пересоздаются так:
+// MICRODELAY_UPDATE_GOLDEN=1 cargo test -p microdelay_engine --test impulse_response
+mod common;
+
+use common::{delay_line_ir, impulse, render_at};
+use microdelay_engine::EngineParams;
+use microdelay_engine::delay_line::Interpolation;
+use std::path::PathBuf;
+
+const SAMPLERATES: [f32; 5] = [44100.0, 48000.0, 88200.0, 96000.0, 192000.0];
+const BLOCK_SIZE: usize = 256;
+const GOLDEN_LEN: usize = 1024;
+// запас на разницу libm между платформами
+const GOLDEN_TOLERANCE: f32 = 1e-5;
+const UPDATE_GOLDEN_VAR: &str = "MICRODELAY_UPDATE_GOLDEN";
+
+fn matrix_ir(params: &EngineParams, samplerate: f32, len: usize) -> Vec<f32> {
+    render_at(params, &impulse(len), BLOCK_SIZE, samplerate)
+}
+
+// Матрица без прямого сигнала; фильтры по умолчанию в обходе, линии - чистые задержки
+fn wet_only(line_count: usize) -> EngineParams {
+    EngineParams {
+        dry: 0.0,
+        line_count,
+        input_sends: [0.0; 8],
+        ..Default::default()
+    }
+}
+
+// Задержки в тестах - целое число отсчетов на всех частотах
+fn ms_to_samples(ms: f32, samplerate: f32) -> usize {
+    (ms * 1e-3 * samplerate).round() as usize
+}
+
+#[test]
+fn delay_line_taps_at_integer_delays() {
+    for interpolation in [
+        Interpolation::Linear,
+        Interpolation::Hermite,
+        Interpolation::Lagrange,
+    ] {
+        for delay in [1, 2, 17, 100] {
+            let ir = delay_line_ir(interpolation, delay as f32, 128);
+            let mut expected = vec![0.0; 128];
+            expected[delay] = 1.0;
+            assert_eq!(ir, expected, "{interpolation:?}, delay {delay}");
+        }
+    }
+}
+
+#[test]
+fn matrix_taps_follow_delay_time() {
+    for samplerate in SAMPLERATES {
+        for delay in [10.0, 20.0, 50.0] {
+            let mut params = wet_only(2);
+            params.input_sends[0] = 100.0;
+            params.lines[0].level = 100.0;
+            params.lines[0].delay = delay;
+
+            let ir = matrix_ir(&params, samplerate, ms_to_samples(60.0, samplerate));
+            let mut expected = vec![0.0; ir.len()];
+            expected[ms_to_samples(delay, samplerate)] = 1.0;
+            assert_eq!(ir, expected, "{samplerate} Hz, {delay} ms");
+        }
+    }
+}
+
+#[test]
+fn feedback_decays_by_send_gain() {
+    for samplerate in SAMPLERATES {
+        for feedback in [-80.0, -30.0, 50.0, 80.0] {
+            let mut params = wet_only(2);
+            params.input_sends[0] = 100.0;
+            params.sends[0][0] = feedback;
+            params.lines[0].level = 100.0;
+            params.lines[0].delay = 10.0;
+
+            let period = ms_to_samples(10.0, samplerate);
+            let ir = matrix_ir(&params, samplerate, 6 * period);
+            for (n, &value) in ir.iter().enumerate() {
+                let echo = n / period;
+                if n % period != 0 || echo == 0 {
+                    assert_eq!(value, 0.0, "{samplerate} Hz, feedback {feedback}, ir[{n}]");
+                    continue;
+                }
+                // каждый круг - одно умножение на посыл, ошибка округления копится по эпсилону
+                let expected = (feedback / 100.0f32).powi(echo as i32 - 1);
+                assert!(
+                    (value - expected).abs() <= f32::EPSILON * echo as f32 * expected.abs(),
+                    "{samplerate} Hz, feedback {feedback}, echo {echo}: {value}, expected {expected}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn negative_cross_send_inverts() {
+    for samplerate in SAMPLERATES {
+        for send in [-100.0, -40.0, 40.0, 100.0] {
+            // A слышна только через посыл в B
+            let mut params = wet_only(2);
+            params.input_sends[0] = 100.0;
+            params.sends[0][1] = send;
+            params.lines[0].delay = 10.0;
+            params.lines[1].delay = 20.0;
+            params.lines[1].level = 100.0;
+
+            let ir = matrix_ir(&params, samplerate, ms_to_samples(40.0, samplerate));
+            let mut expected = vec![0.0; ir.len()];
+            expected[ms_to_samples(30.0, samplerate)] = send / 100.0;
+            assert_eq!(ir, expected, "{samplerate} Hz, send {send}");
+        }
+    }
+}
+
+#[test]
+fn dry_path_is_exact() {
+    for samplerate in SAMPLERATES {
+        for dry in [-100.0, 0.0, 35.0, 100.0] {
+            let params = EngineParams {
+                dry,
+                input_sends: [0.0; 8],
+                ..Default::default()
+            };
+            let ir = matrix_ir(&params, samplerate, 512);
+            assert_eq!(ir[0], dry / 100.0, "{samplerate} Hz, dry {dry}");
+            assert!(ir[1..].iter().all(|&value| value == 0.0));
+        }
+    }
+}
+
+// Две линии с перекрестными посылами обоих знаков, фильтром и дробной задержкой
+fn golden_matrix() -> EngineParams {
+    let mut params = EngineParams::default();
+    params.input_sends[1] = 0.0;
+    params.sends[0][0] = 40.0;
+    params.sends[0][1] = -70.0;
+    params.sends[1][0] = 30.0;
+    params.lines[0].delay = 1.0;
+    params.lines[0].level = 100.0;
+    params.lines[0].lowpass = 8000.0;
+    params.lines[1].delay = 1.3;
+    params.lines[1].level = 60.0;
+    params.lines[1].interpolation = Interpolation::Hermite;
+    params
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.txt"))
+}
+
+// Формат: строки-комментарии с '#', дальше по одному отсчету на строку
+fn assert_matches_golden(name: &str, description: &str, ir: &[f32]) {
+    let path = golden_path(name);
+    if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        let mut text = format!("# {description}\n");
+        ir.iter().for_each(|value| text += &format!("{value:e}\n"));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, text).unwrap();
+        return;
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "can't read {}: {err}, run with {UPDATE_GOLDEN_VAR}=1 to create it",
+            path.display()
+        )
+    });
+    let golden: Vec<f32> = text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.parse().unwrap())
+        .collect();
+
+    assert_eq!(golden.len(), ir.len(), "{name}: length differs");
+    for (n, (value, expected)) in ir.iter().zip(&golden).enumerate() {
+        assert!(
+            (value - expected).abs() <= GOLDEN_TOLERANCE,
+            "{name}: ir[{n}] = {value}, golden {expected}"
+        );
+    }
+}
+
+#[test]
+fn delay_line_matches_golden() {
+    for (interpolation, name) in [
+        (Interpolation::Linear, "linear"),
+        (Interpolation::Hermite, "hermite"),
+        (Interpolation::Lagrange, "lagrange"),
+        (Interpolation::Allpass, "allpass"),
+    ] {
+        let ir = delay_line_ir(interpolation, 10.3, 64);
+        assert_matches_golden(
+            &format!("delay_line_{name}"),
+            &format!("DelayLine, {interpolation:?}, delay 10.3 samples"),
+            &ir,
+        );
+    }
+}
+
+#[test]
+fn matrix_matches_golden() {
+    for samplerate in SAMPLERATES {
+        let ir = matrix_ir(&golden_matrix(), samplerate, GOLDEN_LEN);
+        assert_matches_golden(
+            &format!("matrix_{}", samplerate as u32),
+            &format!("MatrixDelayEngine, golden_matrix(), {samplerate} Hz, mono"),
+            &ir,
+        );
+    }
+}
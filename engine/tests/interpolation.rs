@@ -0,0 +1,129 @@
+mod common;
+
+use microdelay_engine::delay_line::Interpolation;
+use std::f64::consts::PI;
+
+const IR_LEN: usize = 256;
+
+fn impulse_response(interpolation: Interpolation, delay: f32) -> Vec<f32> {
+    common::delay_line_ir(interpolation, delay, IR_LEN)
+}
+
+fn measured_response(ir: &[f32], omega: f64) -> (f64, f64) {
+    ir.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, &h)| {
+        let phase = -omega * n as f64;
+        (re + h as f64 * phase.cos(), im + h as f64 * phase.sin())
+    })
+}
+
+// Ответ КИХ-интерполятора с весами `taps`, где taps[k] стоит на задержке first + k
+fn fir_response(first: f64, taps: &[f64], omega: f64) -> (f64, f64) {
+    taps.iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (k, &w)| {
+            let phase = -omega * (first + k as f64);
+            (re + w * phase.cos(), im + w * phase.sin())
+        })
+}
+
+fn analytic_response(interpolation: Interpolation, delay: f64, omega: f64) -> (f64, f64) {
+    let whole = delay.floor();
+    let t = delay - whole;
+    match interpolation {
+        Interpolation::Linear => fir_response(whole, &[1.0 - t, t], omega),
+        Interpolation::Hermite => fir_response(
+            whole - 1.0,
+            &[
+                (-t * t * t + 2.0 * t * t - t) / 2.0,
+                (3.0 * t * t * t - 5.0 * t * t + 2.0) / 2.0,
+                (-3.0 * t * t * t + 4.0 * t * t + t) / 2.0,
+                (t * t * t - t * t) / 2.0,
+            ],
+            omega,
+        ),
+        Interpolation::Lagrange => fir_response(
+            whole - 1.0,
+            &[
+                -t * (t - 1.0) * (t - 2.0) / 6.0,
+                (t + 1.0) * (t - 1.0) * (t - 2.0) / 2.0,
+                -(t + 1.0) * t * (t - 2.0) / 2.0,
+                (t + 1.0) * t * (t - 1.0) / 6.0,
+            ],
+            omega,
+        ),
+        Interpolation::Allpass => {
+            let (whole, fraction) = if t < 0.5 {
+                (whole - 1.0, t + 1.0)
+            } else {
+                (whole, t)
+            };
+            let a = (1.0 - fraction) / (1.0 + fraction);
+            // z^-whole * (a + z^-1) / (1 + a z^-1)
+            let (num_re, num_im) = (a + omega.cos(), -omega.sin());
+            let (den_re, den_im) = (1.0 + a * omega.cos(), -a * omega.sin());
+            let den = den_re * den_re + den_im * den_im;
+            let (re, im) = (
+                (num_re * den_re + num_im * den_im) / den,
+                (num_im * den_re - num_re * den_im) / den,
+            );
+            let (c, s) = ((omega * whole).cos(), -(omega * whole).sin());
+            (re * c - im * s, re * s + im * c)
+        }
+    }
+}
+
+fn assert_matches_analytic(interpolation: Interpolation) {
+    for delay in [10.3_f32, 17.5, 23.8] {
+        let ir = impulse_response(interpolation, delay);
+        for bin in 1..16 {
+            let omega = PI * bin as f64 / 16.0;
+            let (re, im) = measured_response(&ir, omega);
+            let (expected_re, expected_im) = analytic_response(interpolation, delay as f64, omega);
+            let error = ((re - expected_re).powi(2) + (im - expected_im).powi(2)).sqrt();
+            assert!(
+                error < 1e-3,
+                "{interpolation:?}, delay {delay}, omega {omega:.3}: got ({re:.5}, {im:.5}), expected ({expected_re:.5}, {expected_im:.5})"
+            );
+        }
+    }
+}
+
+#[test]
+fn linear_matches_analytic_response() {
+    assert_matches_analytic(Interpolation::Linear);
+}
+
+#[test]
+fn hermite_matches_analytic_response() {
+    assert_matches_analytic(Interpolation::Hermite);
+}
+
+#[test]
+fn lagrange_matches_analytic_response() {
+    assert_matches_analytic(Interpolation::Lagrange);
+}
+
+#[test]
+fn allpass_matches_analytic_response() {
+    assert_matches_analytic(Interpolation::Allpass);
+}
+
+#[test]
+fn allpass_has_unity_magnitude() {
+    let ir = impulse_response(Interpolation::Allpass, 12.7);
+    for bin in 1..32 {
+        let (re, im) = measured_response(&ir, PI * bin as f64 / 32.0);
+        assert!(((re * re + im * im).sqrt() - 1.0).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn allpass_is_stable_at_shortest_delays() {
+    // при дробной части около 0 полюс подходит к z = -1 и отклик звенит на Найквисте;
+    // с дробной частью не меньше 0.5 полюс не дальше 1/3 и хвост быстро затухает
+    for delay in [1.0_f32, 1.05, 1.2, 1.49, 1.5, 2.05] {
+        let ir = impulse_response(Interpolation::Allpass, delay);
+        let tail: f32 = ir[24..].iter().map(|value| value.abs()).sum();
+        assert!(tail < 1e-6, "delay {delay}: tail {tail}");
+    }
+}
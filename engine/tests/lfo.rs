@@ -0,0 +1,97 @@
+use microdelay_engine::lfo::{Lfo, Shape};
+
+const SAMPLERATE: f32 = 48000.0;
+// период ровно 480 сэмплов
+const RATE: f32 = 100.0;
+const PERIOD: usize = 480;
+const LEN: usize = 4 * PERIOD;
+
+// Значения левого и правого канала при сдвиге фазы offset в градусах
+fn render(shape: Shape, offset: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut lfo = Lfo::default();
+    lfo.init(2, 1, SAMPLERATE);
+    lfo.shape = shape;
+    (0..LEN)
+        .map(|_| {
+            (
+                lfo.next_value(0, RATE, offset / 360.0),
+                lfo.next_value(1, RATE, offset / 360.0),
+            )
+        })
+        .unzip()
+}
+
+// Сэмплы, на которых случайная форма берет новое значение
+fn steps(values: &[f32]) -> Vec<usize> {
+    (1..values.len())
+        .filter(|&n| values[n] != values[n - 1])
+        .collect()
+}
+
+#[test]
+fn periodic_shapes_are_shifted_by_stereo_phase() {
+    for shape in [Shape::Sine, Shape::Triangle, Shape::Saw] {
+        for offset in [0.0, 90.0, 180.0, 270.0] {
+            let (left, right) = render(shape, offset);
+            let shift = (offset / 360.0 * PERIOD as f32) as usize;
+            for n in 0..LEN - shift {
+                // пила рвется на границе периода, там накопленная ошибка фазы меняет сторону
+                if shape == Shape::Saw && left[n + shift].abs() > 0.99 {
+                    continue;
+                }
+                assert!(
+                    (right[n] - left[n + shift]).abs() < 1e-3,
+                    "{shape:?}, {offset} degrees, sample {n}: {} != {}",
+                    right[n],
+                    left[n + shift]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn opposite_phase_inverts_sine() {
+    let (left, right) = render(Shape::Sine, 180.0);
+    for (left, right) in left.iter().zip(&right) {
+        assert!((left + right).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn sample_and_hold_steps_at_shifted_period_start() {
+    for offset in [0.0, 90.0, 180.0] {
+        let (left, right) = render(Shape::SampleAndHold, offset);
+        let shift = (offset / 360.0 * PERIOD as f32) as usize;
+        let left_steps = steps(&left);
+        let right_steps = steps(&right);
+        assert_eq!(left_steps, [PERIOD, 2 * PERIOD, 3 * PERIOD]);
+        // правый канал начинает период раньше на сдвиг фазы
+        for (left_step, right_step) in left_steps.iter().zip(&right_steps) {
+            assert!(
+                right_step.abs_diff(left_step - shift) <= 1,
+                "{offset} degrees: {left_steps:?} vs {right_steps:?}"
+            );
+        }
+        // и значения у каналов свои
+        assert_ne!(left[LEN - 1], right[LEN - 1]);
+    }
+}
+
+#[test]
+fn smooth_random_has_no_jumps_with_stereo_phase() {
+    // переход между значениями занимает весь период: наклон не больше pi / PERIOD
+    let max_step = std::f32::consts::PI / PERIOD as f32 + 1e-4;
+    for offset in [0.0, 90.0, 180.0, 270.0] {
+        let (left, right) = render(Shape::SmoothRandom, offset);
+        for values in [&left, &right] {
+            assert!(
+                values
+                    .windows(2)
+                    .all(|pair| (pair[1] - pair[0]).abs() <= max_step),
+                "{offset} degrees"
+            );
+        }
+        assert_ne!(left, right);
+    }
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use common::{SAMPLERATE, render};
+use microdelay_engine::saturation::{self, Curve, Saturator};
+use microdelay_engine::{EngineParams, MIN_DELAY_TIME};
+use std::f64::consts::{PI, TAU};
+
+// кривые и их наклон в нуле
+const CURVES: [(Curve, f32); 4] = [
+    (Curve::Tanh, 1.0),
+    (Curve::Tube, 1.0),
+    (Curve::HardClip, 1.0),
+    (Curve::Cubic, 1.5),
+];
+
+fn saturated_echo(curve: Curve) -> EngineParams {
+    let mut params = EngineParams {
+        dry: 0.0,
+        ..Default::default()
+    };
+    params.lines[0].level = 100.0;
+    params.lines[0].delay = 10.0;
+    params.lines[0].drive_curve = curve;
+    params
+}
+
+#[test]
+fn saturation_keeps_tap_position() {
+    // тихий импульс проходит кривые почти линейно: фильтры передискретизации размазывают
+    // повтор на несколько сэмплов, но его центр остается на времени линии
+    let mut input = vec![0.0; 2048];
+    input[0] = 1e-3;
+    for (curve, slope) in CURVES {
+        let mut params = saturated_echo(curve);
+        params.sends[0][0] = 50.0;
+        let output = render(&params, &input, 256);
+        for echo in 1..=3 {
+            // повтор каждый раз проходит кривую заново
+            let gain = 1e-3 * slope.powi(echo as i32) * 0.5f32.powi(echo as i32 - 1);
+            let window = &output[echo * 480 - 240..echo * 480 + 240];
+            let peak = window
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+                .map(|(idx, _)| idx)
+                .unwrap();
+            assert!(peak.abs_diff(240) <= 1, "{curve:?}, echo {echo}: {peak}");
+            // центр масс повтора - групповая задержка на нулевой частоте
+            let sum: f32 = window.iter().sum();
+            let moment: f32 = window
+                .iter()
+                .enumerate()
+                .map(|(n, value)| (n as f32 - 240.0) * value)
+                .sum();
+            assert!(
+                (moment / sum).abs() < 1e-2,
+                "{curve:?}, echo {echo}: {}",
+                moment / sum
+            );
+            assert!((sum - gain).abs() < 1e-3 * gain, "{curve:?}, echo {echo}");
+        }
+    }
+}
+
+#[test]
+fn saturation_keeps_short_loops_in_tune() {
+    // основная частота петли - 1 / время линии, на ней повтор должен идти ровно с этой задержкой
+    let floor = saturation::min_delay();
+    assert!(floor < 6.0, "{floor}");
+    let mut input = vec![0.0; 1024];
+    input[0] = 1e-3;
+    // на минимуме время линии упирается в задержку фильтров
+    let delays = [
+        (MIN_DELAY_TIME, floor),
+        (0.125, 6.0),
+        (0.25, 12.0),
+        (0.5, 24.0),
+        (1.0, 48.0),
+    ];
+    for (curve, _) in CURVES {
+        for (delay, period) in delays {
+            let mut params = saturated_echo(curve);
+            params.lines[0].delay = delay;
+            let output = render(&params, &input, 256);
+
+            let omega = TAU / period as f64;
+            let (re, im) = output
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(re, im), (n, &value)| {
+                    let phase = omega * n as f64;
+                    (
+                        re + value as f64 * phase.cos(),
+                        im - value as f64 * phase.sin(),
+                    )
+                });
+            // фаза повтора -omega * period с точностью до оборота
+            let error = (im.atan2(re) + omega * period as f64 + PI).rem_euclid(TAU) - PI;
+            let error = error / omega;
+            assert!(error.abs() < 1e-2, "{curve:?}, {period}: {error}");
+        }
+    }
+}
+
+// Амплитуда частоты frequency в сигнале с целым числом ее периодов
+fn amplitude(samples: &[f32], frequency: f64) -> f64 {
+    let omega = TAU * frequency / SAMPLERATE as f64;
+    let (re, im) = samples
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, &value)| {
+            let phase = omega * n as f64;
+            (
+                re + value as f64 * phase.cos(),
+                im - value as f64 * phase.sin(),
+            )
+        });
+    2.0 * re.hypot(im) / samples.len() as f64
+}
+
+#[test]
+fn oversampling_suppresses_aliasing() {
+    // 7 кГц: гармоники 21 кГц и выше, без передискретизации нечетные
+    // заворачивались бы на 1, 5, 9, 11, 13, 15, 19 и 23 кГц
+    const FUNDAMENTAL: f64 = 7000.0;
+    let sine: Vec<f32> = (0..6000)
+        .map(|n| (TAU * FUNDAMENTAL * n as f64 / SAMPLERATE as f64).sin() as f32)
+        .collect();
+
+    for drive in [0.0, 6.0, 12.0] {
+        let mut saturator = Saturator::default();
+        saturator.init(1, sine.len());
+        saturator.set_curve(Curve::Tanh);
+        saturator.drive_automation_samples.fill(drive);
+        saturator.bias_automation_samples.fill(0.0);
+        saturator.trim_automation_samples.fill(0.0);
+        saturator.prepare_block(sine.len());
+        let output: Vec<f32> = sine
+            .iter()
+            .enumerate()
+            .map(|(n, &value)| saturator.process(value, 0, n))
+            .collect();
+
+        // после разгона фильтров ровно 700 периодов
+        let steady = &output[1200..];
+        let fundamental = amplitude(steady, FUNDAMENTAL);
+        for alias in [
+            1000.0, 5000.0, 9000.0, 11000.0, 13000.0, 15000.0, 19000.0, 23000.0,
+        ] {
+            let ratio = 20.0 * (amplitude(steady, alias) / fundamental).log10();
+            assert!(ratio < -60.0, "drive {drive} dB, {alias} Hz: {ratio:.1} dB");
+        }
+        // гармоника в полосе остается
+        assert!(amplitude(steady, 3.0 * FUNDAMENTAL) > 1e-3 * fundamental);
+    }
+}
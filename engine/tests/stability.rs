@@ -0,0 +1,164 @@
+use microdelay_engine::stability::{LIMITER_THRESHOLD, Safety, WriteLimiter, spectral_radius};
+use microdelay_engine::{EngineParams, MAX_LINES, MatrixDelayEngine};
+
+const SAMPLERATE: f32 = 48000.0;
+
+fn assert_radius<const N: usize>(matrix: &[[f32; N]; N], size: usize, expected: f32) {
+    let radius = spectral_radius(matrix, size);
+    // у дефектных матриц оценка по норме степени сходится медленнее, порядка ln(k) / k
+    assert!(
+        (radius - expected).abs() < 1e-3 * expected.max(1.0),
+        "{matrix:?}: {radius} != {expected}"
+    );
+}
+
+#[test]
+fn zero_and_nilpotent_matrices_have_no_loop() {
+    assert_eq!(spectral_radius(&[[0.0; 4]; 4], 4), 0.0);
+    assert_eq!(spectral_radius(&[[0.0, 1.0], [0.0, 0.0]], 2), 0.0);
+    // строго верхнетреугольная: все собственные числа нулевые, хотя норма большая
+    let mut matrix = [[0.0; 4]; 4];
+    for (row_idx, row) in matrix.iter_mut().enumerate() {
+        for value in row.iter_mut().skip(row_idx + 1) {
+            *value = 0.9;
+        }
+    }
+    assert_eq!(spectral_radius(&matrix, 4), 0.0);
+}
+
+#[test]
+fn rotation_has_complex_pair_radius() {
+    // собственные числа r * e^(+-i*angle), степенной метод на них не сходится
+    for radius in [0.5, 0.99, 1.3] {
+        for angle in [0.3f32, 1.0, std::f32::consts::FRAC_PI_2, 2.5] {
+            let (sin, cos) = angle.sin_cos();
+            let matrix = [[radius * cos, -radius * sin], [radius * sin, radius * cos]];
+            assert_radius(&matrix, 2, radius);
+        }
+    }
+}
+
+#[test]
+fn jordan_block_has_its_eigenvalue() {
+    // норма степени растет как k * 0.9^k, но радиус все равно 0.9
+    assert_radius(&[[0.9, 1.0], [0.0, 0.9]], 2, 0.9);
+    assert_radius(&[[0.5, 1.0, 0.0], [0.0, 0.5, 1.0], [0.0, 0.0, 0.5]], 3, 0.5);
+}
+
+#[test]
+fn negative_diagonal_counts_by_magnitude() {
+    assert_radius(&[[-0.5, 0.0], [0.0, -1.2]], 2, 1.2);
+    assert_radius(&[[-0.95, 0.0], [0.0, 0.3]], 2, 0.95);
+}
+
+#[test]
+fn closed_form_radii_for_every_size() {
+    for size in [2, 4, 8] {
+        let n = size as f32;
+        // все посылы c: собственные числа n * c и нули
+        let mut uniform = [[0.0; MAX_LINES]; MAX_LINES];
+        for row in uniform.iter_mut().take(size) {
+            row[..size].fill(0.1);
+        }
+        assert_radius(&uniform, size, 0.1 * n);
+
+        // g * (I - 2/n): отражение Хаусхолдера, ортогональное, радиус g
+        let mut householder = [[0.0; MAX_LINES]; MAX_LINES];
+        for (row_idx, row) in householder.iter_mut().enumerate().take(size) {
+            for (col_idx, value) in row.iter_mut().enumerate().take(size) {
+                let identity = if row_idx == col_idx { 1.0 } else { 0.0 };
+                *value = 0.8 * (identity - 2.0 / n);
+            }
+        }
+        assert_radius(&householder, size, 0.8);
+
+        // g * циклическая перестановка: собственные числа - g на корнях из единицы
+        let mut cycle = [[0.0; MAX_LINES]; MAX_LINES];
+        for (row_idx, row) in cycle.iter_mut().enumerate().take(size) {
+            row[(row_idx + 1) % size] = 0.7;
+        }
+        assert_radius(&cycle, size, 0.7);
+    }
+}
+
+#[test]
+fn only_active_lines_count() {
+    let mut matrix = [[0.0; MAX_LINES]; MAX_LINES];
+    matrix[0][0] = 0.5;
+    matrix[7][7] = 3.0;
+    assert_radius(&matrix, 2, 0.5);
+    assert_radius(&matrix, 8, 3.0);
+}
+
+#[test]
+fn write_limiter_never_exceeds_threshold() {
+    let mut limiter = WriteLimiter::default();
+    limiter.init(1, SAMPLERATE);
+    for mode in [Safety::SoftClip, Safety::Limiter] {
+        limiter.mode = mode;
+        limiter.reset();
+        for n in 0..48000 {
+            let input = 40.0 * (n as f32 * 0.01).sin() * (n as f32 * 1e-4).cos();
+            let output = limiter.process(input, 0);
+            assert!(output.abs() <= LIMITER_THRESHOLD, "{mode:?}: {output}");
+        }
+    }
+}
+
+#[test]
+fn write_limiter_recovers_after_peak() {
+    let mut limiter = WriteLimiter::default();
+    limiter.init(1, SAMPLERATE);
+    limiter.mode = Safety::Limiter;
+    limiter.process(10.0, 0);
+    // сразу после пика тихий сигнал прижат, через полсекунды проходит как есть
+    assert!(limiter.process(0.5, 0) < 0.1);
+    for _ in 0..24000 {
+        limiter.process(0.0, 0);
+    }
+    assert_eq!(limiter.process(0.5, 0), 0.5);
+
+    limiter.mode = Safety::Off;
+    assert_eq!(limiter.process(10.0, 0), 10.0);
+}
+
+#[test]
+fn runaway_loop_stays_bounded() {
+    // все посылы +100%: радиус петли равен числу линий
+    for line_count in [2, 4, 8] {
+        for safety in [Safety::Off, Safety::SoftClip, Safety::Limiter] {
+            let mut params = EngineParams {
+                dry: 0.0,
+                line_count,
+                safety,
+                sends: [[100.0; MAX_LINES]; MAX_LINES],
+                ..Default::default()
+            };
+            for line in params.lines.iter_mut() {
+                line.level = 100.0;
+                line.delay = 5.0;
+            }
+            let mut engine = MatrixDelayEngine::new(1, 512, SAMPLERATE);
+            let mut output = vec![0.0; SAMPLERATE as usize];
+            output[0] = 1.0;
+            for block in output.chunks_mut(512) {
+                engine.process(&mut [block], &params);
+            }
+            assert!((engine.loop_gain() - line_count as f32).abs() < 1e-3);
+
+            let peak = output
+                .iter()
+                .fold(0.0f32, |peak, value| peak.max(value.abs()));
+            if safety == Safety::Off {
+                // без защиты петля разносится
+                assert!(peak > 1e6, "{line_count} lines: {peak}");
+            } else {
+                // каждая линия пишет не больше порога, на выходе их сумма
+                assert!(
+                    peak <= line_count as f32 * LIMITER_THRESHOLD,
+                    "{line_count} lines, {safety:?}: {peak}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::{impulse, peak_position, render_at};
+use microdelay_engine::tempo_sync::{Division, Mode, TransportInfo, synced_delay_ms};
+use microdelay_engine::{EngineParams, MAX_DELAY_TIME};
+
+// частота пониже, чтобы проверить предельную задержку без миллиона сэмплов
+const SAMPLERATE: f32 = 4000.0;
+
+const DIVISIONS: [(Division, f64); 9] = [
+    (Division::SixtyFourth, 1.0 / 16.0),
+    (Division::ThirtySecond, 1.0 / 8.0),
+    (Division::Sixteenth, 1.0 / 4.0),
+    (Division::Eighth, 1.0 / 2.0),
+    (Division::Quarter, 1.0),
+    (Division::Half, 2.0),
+    (Division::Bar, 4.0),
+    (Division::TwoBars, 8.0),
+    (Division::FourBars, 16.0),
+];
+
+fn transport(tempo: f64, time_signature: Option<(i32, i32)>) -> TransportInfo {
+    TransportInfo {
+        tempo: Some(tempo),
+        time_signature,
+    }
+}
+
+fn assert_close(value: f32, expected: f64) {
+    assert!(
+        (value as f64 - expected).abs() < 1e-3,
+        "{value} != {expected}"
+    );
+}
+
+#[test]
+fn divisions_and_modes_scale_quarter_note() {
+    // четверть при 120 BPM - 500 мс
+    let transport = transport(120.0, Some((4, 4)));
+    for (division, quarters) in DIVISIONS {
+        for (mode, multiplier) in [
+            (Mode::Straight, 1.0),
+            (Mode::Dotted, 1.5),
+            (Mode::Triplet, 2.0 / 3.0),
+        ] {
+            let delay = synced_delay_ms(mode, division, &transport).unwrap();
+            assert_close(delay, 500.0 * quarters * multiplier);
+        }
+    }
+}
+
+#[test]
+fn bars_follow_time_signature() {
+    // 6/8 - три четверти в такте, 7/4 - семь; без размера такт считается 4/4
+    for (time_signature, quarters_per_bar) in
+        [(Some((6, 8)), 3.0), (Some((7, 4)), 7.0), (None, 4.0)]
+    {
+        let transport = transport(60.0, time_signature);
+        for (division, bars) in [
+            (Division::Bar, 1.0),
+            (Division::TwoBars, 2.0),
+            (Division::FourBars, 4.0),
+        ] {
+            let delay = synced_delay_ms(Mode::Straight, division, &transport).unwrap();
+            assert_close(delay, 1000.0 * quarters_per_bar * bars);
+        }
+        // доли короче такта от размера не зависят
+        let delay = synced_delay_ms(Mode::Straight, Division::Eighth, &transport).unwrap();
+        assert_close(delay, 500.0);
+    }
+}
+
+#[test]
+fn free_mode_and_missing_tempo_fall_back_to_milliseconds() {
+    let transport = transport(120.0, None);
+    assert_eq!(
+        synced_delay_ms(Mode::Free, Division::Quarter, &transport),
+        None
+    );
+    for tempo in [None, Some(0.0), Some(-90.0)] {
+        let transport = TransportInfo {
+            tempo,
+            time_signature: Some((4, 4)),
+        };
+        assert_eq!(
+            synced_delay_ms(Mode::Dotted, Division::Quarter, &transport),
+            None
+        );
+    }
+}
+
+fn echo_position(params: &EngineParams, len: usize) -> usize {
+    peak_position(&render_at(params, &impulse(len), 1024, SAMPLERATE))
+}
+
+fn synced_echo(sync: Mode, division: Division, tempo: Option<f64>) -> EngineParams {
+    let mut params = EngineParams {
+        dry: 0.0,
+        ..Default::default()
+    };
+    params.lines[0].level = 100.0;
+    params.lines[0].delay = 250.0;
+    params.lines[0].sync = sync;
+    params.lines[0].division = division;
+    params.transport = TransportInfo {
+        tempo,
+        time_signature: Some((4, 4)),
+    };
+    params
+}
+
+#[test]
+fn engine_uses_knob_time_without_tempo() {
+    let params = synced_echo(Mode::Triplet, Division::Half, None);
+    assert_eq!(echo_position(&params, 4096), 1000);
+}
+
+#[test]
+fn slow_tempo_is_clamped_to_max_delay_time() {
+    // четыре такта при 20 BPM - 48 с, линия держит не больше MAX_DELAY_TIME
+    let params = synced_echo(Mode::Dotted, Division::FourBars, Some(20.0));
+    let max_delay = (MAX_DELAY_TIME * SAMPLERATE / 1e3) as usize;
+    assert_eq!(echo_position(&params, max_delay + 4096), max_delay);
+}
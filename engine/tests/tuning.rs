@@ -0,0 +1,133 @@
+use microdelay_engine::tuning::{NoteTuning, TuningSettings};
+use microdelay_engine::{EngineParams, MatrixDelayEngine};
+
+const SAMPLERATE: f32 = 48000.0;
+
+fn tuning(settings: TuningSettings) -> NoteTuning {
+    let mut tuning = NoteTuning::default();
+    tuning.init(SAMPLERATE);
+    tuning.set_settings(TuningSettings {
+        enabled: true,
+        ..settings
+    });
+    tuning
+}
+
+fn period(frequency: f32) -> f32 {
+    SAMPLERATE / frequency
+}
+
+fn assert_close(value: f32, expected: f32) {
+    assert!((value - expected).abs() < 1e-3, "{value} != {expected}");
+}
+
+#[test]
+fn note_sets_period_of_both_lines() {
+    let mut tuning = tuning(TuningSettings {
+        interval_b: 7.0,
+        ..Default::default()
+    });
+    assert_eq!(tuning.next_delays(), None);
+
+    tuning.note_on(69, 1.0);
+    let (delay_a, delay_b) = tuning.next_delays().unwrap();
+    assert_close(delay_a, period(440.0));
+    assert_close(delay_b, period(440.0 * 2f32.powf(7.0 / 12.0)));
+}
+
+#[test]
+fn fine_tune_is_in_cents() {
+    let mut tuning = tuning(TuningSettings {
+        fine_tune: -100.0,
+        ..Default::default()
+    });
+    tuning.note_on(70, 1.0);
+    let (delay_a, delay_b) = tuning.next_delays().unwrap();
+    assert_close(delay_a, period(440.0));
+    assert_close(delay_b, period(440.0));
+}
+
+#[test]
+fn glide_reaches_note_in_glide_time() {
+    let mut tuning = tuning(TuningSettings {
+        glide: 100.0,
+        ..Default::default()
+    });
+    tuning.note_on(57, 1.0);
+    assert_close(tuning.next_delays().unwrap().0, period(220.0));
+
+    tuning.note_on(69, 1.0);
+    let mut previous = period(220.0);
+    for _ in 0..4800 {
+        let (delay, _) = tuning.next_delays().unwrap();
+        assert!(delay < previous);
+        previous = delay;
+    }
+    assert_eq!(tuning.next_delays().unwrap().0, previous);
+    assert_close(previous, period(440.0));
+}
+
+#[test]
+fn released_note_returns_to_held_one() {
+    let mut tuning = tuning(TuningSettings::default());
+    tuning.note_on(57, 1.0);
+    tuning.note_on(69, 1.0);
+    tuning.note_off(69);
+    assert_close(tuning.next_delays().unwrap().0, period(220.0));
+
+    // после последней ноты высота остается
+    tuning.note_off(57);
+    assert_close(tuning.next_delays().unwrap().0, period(220.0));
+}
+
+#[test]
+fn excitation_lasts_one_period() {
+    let mut tuning = tuning(TuningSettings {
+        excitation: 50.0,
+        ..Default::default()
+    });
+    tuning.note_on(69, 0.5);
+    let burst: Vec<f32> = (0..200).map(|_| tuning.next_excitation()).collect();
+    let len = period(440.0).ceil() as usize;
+    assert!(burst[..len].iter().all(|value| value.abs() <= 0.25));
+    assert!(burst[..len].iter().any(|value| value.abs() > 0.1));
+    assert!(burst[len..].iter().all(|&value| value == 0.0));
+}
+
+#[test]
+fn engine_plays_note_without_input() {
+    let mut params = EngineParams {
+        dry: 0.0,
+        ..Default::default()
+    };
+    params.lines[0].level = 100.0;
+    params.lines[0].lowpass = 20000.0;
+    params.tuning = TuningSettings {
+        enabled: true,
+        excitation: 100.0,
+        ..Default::default()
+    };
+
+    let mut engine = MatrixDelayEngine::new(1, 256, SAMPLERATE);
+    let mut output = vec![0.0; 1024];
+    for (block_idx, block) in output.chunks_mut(256).enumerate() {
+        engine.prepare_block(1, block.len(), &params);
+        for (sample_idx, sample) in block.iter_mut().enumerate() {
+            if block_idx == 0 && sample_idx == 10 {
+                engine.note_on(69, 1.0);
+            }
+            let mut frame = [*sample];
+            engine.process_frame(&mut frame, sample_idx);
+            *sample = frame[0];
+        }
+    }
+
+    // импульс длиной в период выходит из линии A через период после ноты
+    let start = 10 + period(440.0).round() as usize;
+    assert!(output[..start - 2].iter().all(|&value| value == 0.0));
+    assert!(
+        output[start..start + 100]
+            .iter()
+            .any(|value| value.abs() > 0.1)
+    );
+}
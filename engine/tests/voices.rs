@@ -0,0 +1,332 @@
+use microdelay_engine::tuning::{TuningSettings, note_period};
+use microdelay_engine::voices::{VoicePool, VoiceTarget};
+use microdelay_engine::{EngineParams, MatrixDelayEngine};
+
+const SAMPLERATE: f32 = 48000.0;
+const BLOCK_SIZE: usize = 256;
+
+fn settings(voices: usize) -> TuningSettings {
+    TuningSettings {
+        enabled: true,
+        voices,
+        excitation: 100.0,
+        ..Default::default()
+    }
+}
+
+fn pool(voices: usize) -> VoicePool {
+    let mut pool = VoicePool::default();
+    pool.init(2, BLOCK_SIZE, SAMPLERATE);
+    pool.set_settings(settings(voices));
+    pool
+}
+
+fn sounding_notes(pool: &VoicePool) -> Vec<u8> {
+    let mut notes: Vec<u8> = pool.voices.iter().filter_map(|voice| voice.note).collect();
+    notes.sort();
+    notes
+}
+
+// Резонатор на линии A с сильной обратной связью. Вход в тестах тихий, звучат только импульсы нот
+fn resonator(voices: usize) -> EngineParams {
+    let mut params = EngineParams {
+        dry: 0.0,
+        line_count: 1,
+        tuning: settings(voices),
+        ..Default::default()
+    };
+    params.sends[0][0] = 90.0;
+    params.lines[0].level = 100.0;
+    params
+}
+
+// Ноты (сэмпл, нота, нажата) по ходу рендера
+fn render(params: &EngineParams, notes: &[(usize, u8, bool)], len: usize) -> Vec<f32> {
+    render_input(params, notes, vec![0.0; len])
+}
+
+fn render_input(params: &EngineParams, notes: &[(usize, u8, bool)], input: Vec<f32>) -> Vec<f32> {
+    let mut engine = MatrixDelayEngine::new(1, BLOCK_SIZE, SAMPLERATE);
+    let mut output = input;
+    for (block_idx, block) in output.chunks_mut(BLOCK_SIZE).enumerate() {
+        engine.prepare_block(1, block.len(), params);
+        for (sample_idx, sample) in block.iter_mut().enumerate() {
+            let position = block_idx * BLOCK_SIZE + sample_idx;
+            for &(_, note, pressed) in notes.iter().filter(|(at, ..)| *at == position) {
+                if pressed {
+                    engine.note_on(note, 1.0);
+                } else {
+                    engine.note_off(note);
+                }
+            }
+            let mut frame = [*sample];
+            engine.process_frame(&mut frame, sample_idx);
+            *sample = frame[0];
+        }
+    }
+    output
+}
+
+#[test]
+fn notes_get_own_voices() {
+    let mut pool = pool(8);
+    pool.set_settings(TuningSettings {
+        interval_b: 12.0,
+        ..settings(8)
+    });
+    for note in [57, 64, 69] {
+        pool.note_on(note, 1.0);
+    }
+    assert_eq!(sounding_notes(&pool), [57, 64, 69]);
+
+    let voice = pool
+        .voices
+        .iter()
+        .find(|voice| voice.note == Some(69))
+        .unwrap();
+    assert!((voice.delays[0] - SAMPLERATE / 440.0).abs() < 1e-3);
+    assert!((voice.delays[1] - SAMPLERATE / 880.0).abs() < 1e-3);
+    assert!((voice.delays[1] - note_period(81.0, SAMPLERATE)).abs() < 1e-3);
+}
+
+#[test]
+fn released_voice_is_stolen_first() {
+    let mut pool = pool(2);
+    pool.note_on(60, 1.0);
+    pool.note_on(62, 1.0);
+    pool.note_off(62);
+    pool.note_on(64, 1.0);
+    assert_eq!(sounding_notes(&pool), [60, 64]);
+
+    // все удерживаются - крадется самый старый
+    pool.note_on(65, 1.0);
+    assert_eq!(sounding_notes(&pool), [64, 65]);
+}
+
+#[test]
+fn quietest_released_voice_is_stolen() {
+    let mut pool = pool(2);
+    pool.note_on(60, 1.0);
+    pool.note_on(62, 1.0);
+    pool.note_off(60);
+    pool.note_off(62);
+    // старшая нота еще звучит, младшая уже молчит
+    for _ in 0..100 {
+        if let Some(voice) = pool.voices.iter_mut().find(|voice| voice.note == Some(60)) {
+            voice.track(0.5);
+        }
+        pool.finish_frame();
+    }
+    pool.note_on(64, 1.0);
+    assert_eq!(sounding_notes(&pool), [60, 64]);
+}
+
+#[test]
+fn stolen_voice_fades_out() {
+    // без шумовых импульсов линии звучат только низким синусом со входа, он гладкий
+    let mut params = resonator(2);
+    params.tuning.excitation = 0.0;
+    let input: Vec<f32> = (0..8192)
+        .map(|n| (std::f32::consts::TAU * 50.0 * n as f32 / SAMPLERATE).sin())
+        .collect();
+    let notes = [(0, 57, true), (0, 64, true), (4800, 69, true)];
+    let output = render_input(&params, &notes, input);
+
+    // скачок за сэмпл вокруг кражи не больше, чем у гладкого сигнала до нее
+    let max_step = |samples: &[f32]| {
+        samples
+            .windows(2)
+            .fold(0.0f32, |step, pair| step.max((pair[1] - pair[0]).abs()))
+    };
+    let before = max_step(&output[4000..4800]);
+    assert!(before > 0.0);
+    let around = max_step(&output[4700..4900]);
+    assert!(around < 2.0 * before, "{around} vs {before}");
+}
+
+#[test]
+fn fewer_voices_silence_the_rest() {
+    let mut pool = pool(4);
+    (60..64).for_each(|note| pool.note_on(note, 1.0));
+    pool.set_settings(settings(2));
+    assert_eq!(pool.active_count(), 2);
+
+    // одноголосый режим - это линии A и B, голоса не нужны
+    pool.set_settings(settings(1));
+    assert!(!pool.is_polyphonic());
+    assert_eq!(pool.active_count(), 0);
+}
+
+#[test]
+fn voices_ring_independently() {
+    let params = resonator(4);
+    let len = 8 * BLOCK_SIZE;
+    let a = render(&params, &[(10, 57, true)], len);
+    let e = render(&params, &[(300, 64, true)], len);
+    let chord = render(&params, &[(10, 57, true), (300, 64, true)], len);
+
+    // без насыщения и ограничителя голоса линейны: аккорд - сумма отдельных нот
+    assert!(chord.iter().any(|value| value.abs() > 0.1));
+    for ((chord, a), e) in chord.iter().zip(&a).zip(&e) {
+        assert!((chord - (a + e)).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn released_voice_decays_by_feedback() {
+    let mut params = resonator(4);
+    params.sends[0][0] = 50.0;
+    let mut engine = MatrixDelayEngine::new(1, BLOCK_SIZE, SAMPLERATE);
+    let mut block = [0.0; BLOCK_SIZE];
+
+    engine.prepare_block(1, BLOCK_SIZE, &params);
+    engine.note_on(69, 1.0);
+    engine.note_off(69);
+    assert_eq!(engine.active_voices(), 1);
+    let mut process = |engine: &mut MatrixDelayEngine| {
+        block.fill(0.0);
+        engine.process(&mut [&mut block], &params);
+    };
+    (0..4).for_each(|_| process(&mut engine));
+    assert_eq!(engine.active_voices(), 1);
+
+    // при 50% каждый период тише на 6 дБ: до -100 дБ около 17 периодов, потом еще одна петля тишины
+    (0..40).for_each(|_| process(&mut engine));
+    assert_eq!(engine.active_voices(), 0);
+    assert!(block.iter().all(|value| value.abs() < 1e-4));
+}
+
+#[test]
+fn released_voice_frees_under_continuous_input() {
+    // вход звучит все время: после отпускания он закрывается и голос затухает по обратной связи
+    let mut params = resonator(4);
+    params.sends[0][0] = 50.0;
+    params.tuning.excitation = 0.0;
+    let mut engine = MatrixDelayEngine::new(1, BLOCK_SIZE, SAMPLERATE);
+    let mut position = 0;
+    let mut process = |engine: &mut MatrixDelayEngine| {
+        let mut block: Vec<f32> = (position..position + BLOCK_SIZE)
+            .map(|n| 0.5 * (std::f32::consts::TAU * 220.0 * n as f32 / SAMPLERATE).sin())
+            .collect();
+        position += BLOCK_SIZE;
+        engine.process(&mut [&mut block], &params);
+    };
+
+    engine.prepare_block(1, BLOCK_SIZE, &params);
+    engine.note_on(69, 1.0);
+    (0..8).for_each(|_| process(&mut engine));
+    engine.note_off(69);
+    (0..8).for_each(|_| process(&mut engine));
+    assert_eq!(engine.active_voices(), 1);
+    (0..40).for_each(|_| process(&mut engine));
+    assert_eq!(engine.active_voices(), 0);
+}
+
+#[test]
+fn other_lines_send_into_voices() {
+    // вход только в линию C, она посылает в A; без посыла голос молчит
+    let mut params = resonator(4);
+    params.line_count = 3;
+    params.tuning.excitation = 0.0;
+    params.input_sends = [0.0; 8];
+    params.input_sends[2] = 100.0;
+    params.lines[2].delay = 1.0;
+    let notes = [(0, 69, true)];
+    let mut input = vec![0.0; 4096];
+    input[0] = 1.0;
+
+    let silent = render_input(&params, &notes, input.clone());
+    assert!(silent.iter().all(|&value| value == 0.0));
+
+    params.sends[2][0] = 100.0;
+    let output = render_input(&params, &notes, input);
+    // импульс проходит линию C (48 отсчетов) и период ноты в голосе
+    let first = output.iter().position(|value| value.abs() > 1e-3).unwrap();
+    let expected = 48.0 + SAMPLERATE / 440.0;
+    assert!(
+        (first as f32 - expected).abs() <= 2.0,
+        "{first} vs {expected}"
+    );
+}
+
+#[test]
+fn voices_glide_from_last_note() {
+    let mut pool = pool(4);
+    pool.set_settings(TuningSettings {
+        glide: 10.0,
+        ..settings(4)
+    });
+    pool.note_on(57, 1.0);
+    pool.note_on(69, 1.0);
+    let delay = |pool: &VoicePool| {
+        pool.voices
+            .iter()
+            .find(|voice| voice.note == Some(69))
+            .unwrap()
+            .delays[0]
+    };
+
+    // 10 мс - 480 шагов, равномерно в полутонах
+    assert!((delay(&pool) - note_period(57.0, SAMPLERATE)).abs() < 1e-3);
+    (0..240).for_each(|_| pool.finish_frame());
+    assert!((delay(&pool) - note_period(63.0, SAMPLERATE)).abs() < 1e-2);
+    (0..240).for_each(|_| pool.finish_frame());
+    assert_eq!(delay(&pool), note_period(69.0, SAMPLERATE));
+    // первая нота не скользит
+    let first = pool
+        .voices
+        .iter()
+        .find(|voice| voice.note == Some(57))
+        .unwrap();
+    assert_eq!(first.delays[0], note_period(57.0, SAMPLERATE));
+}
+
+#[test]
+fn notes_take_own_modulation() {
+    let mut pool = pool(4);
+    pool.set_settings(TuningSettings {
+        excitation: 0.0,
+        ..settings(4)
+    });
+    pool.note_on(57, 1.0);
+    pool.note_on(69, 1.0);
+    let voice_idx = |pool: &VoicePool, note| {
+        pool.voices
+            .iter()
+            .position(|voice| voice.note == Some(note))
+            .unwrap()
+    };
+
+    // +100 центов только у одной ноты
+    pool.modulate(69, VoiceTarget::FineTune, 100.0);
+    let (a, e) = (voice_idx(&pool, 57), voice_idx(&pool, 69));
+    assert_eq!(pool.voices[a].delays[0], note_period(57.0, SAMPLERATE));
+    assert!((pool.voices[e].delays[0] - note_period(70.0, SAMPLERATE)).abs() < 1e-3);
+
+    // громкость импульса, пришедшая вместе с нотой, запускает его только у этой ноты
+    pool.note_on(60, 1.0);
+    pool.note_on(64, 1.0);
+    pool.modulate(64, VoiceTarget::Excitation, 50.0);
+    pool.next_excitations();
+    assert_eq!(pool.excitations[voice_idx(&pool, 60)], 0.0);
+    assert!(pool.excitations[voice_idx(&pool, 64)] != 0.0);
+}
+
+#[test]
+fn ended_notes_are_reported_once() {
+    let mut pool = pool(2);
+    pool.note_on(60, 1.0);
+    pool.note_on(62, 1.0);
+    assert_eq!(pool.ended_notes().count(), 0);
+    // украденная нота заканчивается для хоста сразу
+    pool.note_on(64, 1.0);
+    assert_eq!(pool.ended_notes().collect::<Vec<_>>(), [60]);
+    assert_eq!(pool.ended_notes().count(), 0);
+
+    pool.set_settings(settings(1));
+    assert_eq!(pool.ended_notes().collect::<Vec<_>>(), [62, 64]);
+    // в одноголосом режиме нота заканчивается при отпускании
+    pool.note_on(67, 1.0);
+    pool.note_off(67);
+    assert_eq!(pool.ended_notes().collect::<Vec<_>>(), [67]);
+}
@@ -0,0 +1,208 @@
+use microdelay::params::DParams;
+use microdelay::preset::{self, Preset};
+use microdelay::wav::AudioFile;
+use microdelay_engine::tempo_sync::TransportInfo;
+use microdelay_engine::{EngineParams, MAX_CHANNELS, MatrixDelayEngine};
+
+const USAGE: &str = "\
+Usage: microdelay-render <INPUT.wav> <OUTPUT.wav> [OPTIONS]
+
+Runs a WAV file through the MicroDelay engine and writes a 32-bit float WAV.
+
+Options:
+      --preset <PATH|NAME>     Load a JSON preset file or a factory preset by name
+      --set <ID>=<VALUE>       Set a parameter, applied after the preset (can be repeated)
+      --tail <SECONDS>         Silence appended to the input so the feedback can decay [default: 2]
+      --tempo <BPM>            Host tempo for synced delay times [default: 120]
+      --time-signature <N/D>   Host time signature [default: 4/4]
+      --block-size <SAMPLES>   Processing block size [default: 512]
+      --list-params            Print all parameter IDs and exit
+      --list-presets           Print the factory preset names and exit
+  -h, --help                   Print this help
+
+Values use the units shown on the knobs (%, ms, Hz, dB); switches take their IDs or names,
+e.g. --set line_a_delay=250 --set line_a_sync=dotted --set line_count=4.
+";
+
+struct Options {
+    input: String,
+    output: String,
+    presets: Vec<String>,
+    overrides: Vec<(String, String)>,
+    tail: f64,
+    transport: TransportInfo,
+    block_size: usize,
+}
+
+fn main() {
+    let options = parse_args();
+
+    let input = AudioFile::read(&options.input)
+        .unwrap_or_else(|err| exit_with_error(&format!("can't read {}: {err}", options.input)));
+    if input.channels.is_empty() {
+        exit_with_error(&format!("{} has no channels", options.input));
+    }
+    if input.channels.len() > MAX_CHANNELS {
+        exit_with_error(&format!(
+            "{} has {} channels, at most {MAX_CHANNELS} are supported",
+            options.input,
+            input.channels.len()
+        ));
+    }
+
+    // значения по умолчанию те же, что у плагина
+    let mut params = DParams::default().engine_params(options.transport);
+    for path in &options.presets {
+        load_preset(&mut params, path)
+            .unwrap_or_else(|err| exit_with_error(&format!("can't load preset {path}: {err}")));
+    }
+    for (id, value) in &options.overrides {
+        preset::apply_param(&mut params, id, value).unwrap_or_else(|err| exit_with_error(&err));
+    }
+
+    let output = render(&input, &params, options.tail, options.block_size);
+    output
+        .write(&options.output)
+        .unwrap_or_else(|err| exit_with_error(&format!("can't write {}: {err}", options.output)));
+}
+
+fn render(input: &AudioFile, params: &EngineParams, tail: f64, block_size: usize) -> AudioFile {
+    let tail_len = (tail * input.samplerate as f64).round() as usize;
+    let total_len = input.len() + tail_len;
+
+    let mut channels: Vec<Vec<f32>> = input
+        .channels
+        .iter()
+        .map(|channel| {
+            let mut channel = channel.clone();
+            channel.resize(total_len, 0.0);
+            channel
+        })
+        .collect();
+
+    let mut engine = MatrixDelayEngine::new(channels.len(), block_size, input.samplerate as f32);
+    for block_start in (0..total_len).step_by(block_size) {
+        let block_end = (block_start + block_size).min(total_len);
+        let mut block: Vec<&mut [f32]> = channels
+            .iter_mut()
+            .map(|channel| &mut channel[block_start..block_end])
+            .collect();
+        engine.process(&mut block, params);
+    }
+
+    AudioFile {
+        samplerate: input.samplerate,
+        channels,
+    }
+}
+
+// Сначала файл, потом заводской пресет с таким именем
+fn load_preset(params: &mut EngineParams, path: &str) -> Result<(), String> {
+    let preset = match std::fs::read_to_string(path) {
+        Ok(json) => Preset::from_json(&json)?,
+        Err(err) => preset::factory_bank()
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(path))
+            .ok_or_else(|| err.to_string())?,
+    };
+    preset.apply(params)
+}
+
+fn parse_args() -> Options {
+    let mut args = std::env::args().skip(1);
+    let mut paths = Vec::new();
+    let mut options = Options {
+        input: String::new(),
+        output: String::new(),
+        presets: Vec::new(),
+        overrides: Vec::new(),
+        tail: 2.0,
+        transport: TransportInfo {
+            tempo: Some(120.0),
+            time_signature: Some((4, 4)),
+        },
+        block_size: 512,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                std::process::exit(0);
+            }
+            "--list-params" => {
+                preset::param_ids().iter().for_each(|id| println!("{id}"));
+                std::process::exit(0);
+            }
+            "--list-presets" => {
+                preset::factory_bank()
+                    .iter()
+                    .for_each(|preset| println!("{}", preset.name));
+                std::process::exit(0);
+            }
+            "--preset" => options.presets.push(expect_value(&mut args, &arg)),
+            "--set" => {
+                let assignment = expect_value(&mut args, &arg);
+                let Some((id, value)) = assignment.split_once('=') else {
+                    exit_with_error(&format!("expected <ID>=<VALUE>, got {assignment}"));
+                };
+                options
+                    .overrides
+                    .push((id.trim().to_string(), value.to_string()));
+            }
+            "--tail" => {
+                let value = expect_value(&mut args, &arg);
+                match value.parse::<f64>() {
+                    Ok(seconds) if seconds >= 0.0 => options.tail = seconds,
+                    _ => exit_with_error(&format!("invalid value for --tail: {value}")),
+                }
+            }
+            "--tempo" => {
+                let value = expect_value(&mut args, &arg);
+                match value.parse::<f64>() {
+                    Ok(tempo) if tempo > 0.0 => options.transport.tempo = Some(tempo),
+                    _ => exit_with_error(&format!("invalid value for --tempo: {value}")),
+                }
+            }
+            "--time-signature" => {
+                let value = expect_value(&mut args, &arg);
+                let signature = value
+                    .split_once('/')
+                    .and_then(|(n, d)| Some((n.parse::<i32>().ok()?, d.parse::<i32>().ok()?)))
+                    .filter(|(n, d)| *n > 0 && *d > 0);
+                match signature {
+                    Some(signature) => options.transport.time_signature = Some(signature),
+                    None => {
+                        exit_with_error(&format!("invalid value for --time-signature: {value}"))
+                    }
+                }
+            }
+            "--block-size" => {
+                let value = expect_value(&mut args, &arg);
+                match value.parse::<usize>() {
+                    Ok(size) if size > 0 => options.block_size = size,
+                    _ => exit_with_error(&format!("invalid value for --block-size: {value}")),
+                }
+            }
+            _ if arg.starts_with('-') => exit_with_error(&format!("unknown option {arg}")),
+            _ => paths.push(arg),
+        }
+    }
+
+    let [input, output]: [String; 2] = paths
+        .try_into()
+        .unwrap_or_else(|_| exit_with_error("expected an input and an output file"));
+    options.input = input;
+    options.output = output;
+    options
+}
+
+fn expect_value(args: &mut impl Iterator<Item = String>, flag: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| exit_with_error(&format!("{flag} requires a value")))
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {message}\n\n{USAGE}");
+    std::process::exit(1);
+}
@@ -0,0 +1,389 @@
+// Схема сигнала как в res/Delay.png: вход, линии A и B, выход и провода-посылы между ними.
+// Толщина провода - величина посыла, цвет - знак. Перетаскивание провода вверх-вниз меняет
+// посыл, перетаскивание линии - ее время, двойной щелчок возвращает значение по умолчанию
+use nih_plug::prelude::{FloatParam, Param, ParamSetter};
+use nih_plug_egui::egui::{
+    self, Align2, Color32, CornerRadius, FontId, Pos2, Rect, Sense, Shape, Stroke, StrokeKind,
+    Vec2, pos2, vec2,
+};
+use std::f32::consts::PI;
+
+use crate::params::DParams;
+
+pub const COLOR_A: Color32 = Color32::from_rgb(150, 255, 0); // Ярко-зеленый
+pub const COLOR_B: Color32 = Color32::from_rgb(150, 0, 255); // Фиолетовый
+pub const COLOR_DRY: Color32 = Color32::from_rgb(0, 255, 255); // Циан (центр)
+// отрицательный посыл переворачивает фазу
+const COLOR_NEGATIVE: Color32 = Color32::from_rgb(255, 90, 60);
+// вход, выход и стрелки
+const COLOR_IO: Color32 = Color32::from_rgb(235, 222, 222);
+
+// Схема нарисована в этих координатах и масштабируется под ширину редактора
+const SCHEME_SIZE: Vec2 = vec2(480.0, 400.0);
+const MAX_SCALE: f32 = 1.25;
+const KNOB_RADIUS: f32 = 16.0;
+const DRY_KNOB_RADIUS: f32 = 30.0;
+// на сколько точек экрана от провода он еще ловит указатель
+const HIT_DISTANCE: f32 = 8.0;
+// перетаскивание на столько точек схемы проходит весь диапазон, с Shift - в 10 раз точнее
+const DRAG_RANGE: f32 = 200.0;
+
+const INPUT: [Pos2; 5] = [
+    pos2(195.0, 0.0),
+    pos2(285.0, 0.0),
+    pos2(285.0, 18.0),
+    pos2(240.0, 52.0),
+    pos2(195.0, 18.0),
+];
+const OUTPUT: [Pos2; 5] = [
+    pos2(195.0, 400.0),
+    pos2(195.0, 375.0),
+    pos2(240.0, 340.0),
+    pos2(285.0, 375.0),
+    pos2(285.0, 400.0),
+];
+const LINE_A: Rect = Rect::from_min_max(pos2(45.0, 115.0), pos2(165.0, 258.0));
+const LINE_B: Rect = Rect::from_min_max(pos2(320.0, 115.0), pos2(440.0, 258.0));
+
+// Провода: ломаная от источника к получателю, стрелка на последнем отрезке
+const IN_TO_A: [Pos2; 3] = [pos2(240.0, 40.0), pos2(105.0, 40.0), pos2(105.0, 115.0)];
+const IN_TO_B: [Pos2; 3] = [pos2(240.0, 40.0), pos2(380.0, 40.0), pos2(380.0, 115.0)];
+const IN_TO_OUT: [Pos2; 2] = [pos2(240.0, 40.0), pos2(240.0, 350.0)];
+const A_TO_B: [Pos2; 2] = [pos2(150.0, 128.0), pos2(335.0, 128.0)];
+const B_TO_A: [Pos2; 2] = [pos2(335.0, 152.0), pos2(150.0, 152.0)];
+const A_TO_A: [Pos2; 4] = [
+    pos2(45.0, 235.0),
+    pos2(20.0, 235.0),
+    pos2(20.0, 100.0),
+    pos2(90.0, 100.0),
+];
+const B_TO_B: [Pos2; 4] = [
+    pos2(440.0, 235.0),
+    pos2(460.0, 235.0),
+    pos2(460.0, 100.0),
+    pos2(395.0, 100.0),
+];
+const A_TO_OUT: [Pos2; 3] = [pos2(105.0, 258.0), pos2(105.0, 357.0), pos2(240.0, 357.0)];
+const B_TO_OUT: [Pos2; 3] = [pos2(380.0, 258.0), pos2(380.0, 357.0), pos2(240.0, 357.0)];
+
+enum Element {
+    // посыл: ломаная и ручка на ней
+    Wire {
+        points: &'static [Pos2],
+        knob: Pos2,
+        knob_radius: f32,
+    },
+    // линия задержки, ручка внутри - время
+    Line {
+        rect: Rect,
+        name: &'static str,
+    },
+}
+
+struct Control<'a> {
+    param: &'a FloatParam,
+    color: Color32,
+    shape: Element,
+}
+
+impl Control<'_> {
+    // Расстояние от точки схемы до провода или линии, 0 - внутри
+    fn distance(&self, point: Pos2) -> f32 {
+        match &self.shape {
+            Element::Wire {
+                points,
+                knob,
+                knob_radius,
+            } => points
+                .windows(2)
+                .map(|segment| distance_to_segment(point, segment[0], segment[1]))
+                .fold((point.distance(*knob) - knob_radius).max(0.0), f32::min),
+            Element::Line { rect, .. } => rect.distance_to_pos(point),
+        }
+    }
+}
+
+// Перетаскиваемый элемент и его нормированное значение без округления до шагов хоста
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    control_idx: usize,
+    normalized: f32,
+}
+
+fn controls(params: &DParams) -> [Control<'_>; 11] {
+    let wire = |param, color, points: &'static [Pos2], knob| Control {
+        param,
+        color,
+        shape: Element::Wire {
+            points,
+            knob,
+            knob_radius: KNOB_RADIUS,
+        },
+    };
+    let matrix = &params.matrix;
+    [
+        Control {
+            param: &params.line_a.delay,
+            color: COLOR_A,
+            shape: Element::Line {
+                rect: LINE_A,
+                name: "A",
+            },
+        },
+        Control {
+            param: &params.line_b.delay,
+            color: COLOR_B,
+            shape: Element::Line {
+                rect: LINE_B,
+                name: "B",
+            },
+        },
+        wire(&matrix.input[0], COLOR_A, &IN_TO_A, IN_TO_A[1]),
+        wire(&matrix.input[1], COLOR_B, &IN_TO_B, IN_TO_B[1]),
+        Control {
+            param: &params.in_send_out,
+            color: COLOR_DRY,
+            shape: Element::Wire {
+                points: &IN_TO_OUT,
+                knob: pos2(240.0, 232.0),
+                knob_radius: DRY_KNOB_RADIUS,
+            },
+        },
+        wire(matrix.send(0, 1), COLOR_A, &A_TO_B, pos2(192.0, 128.0)),
+        wire(matrix.send(1, 0), COLOR_B, &B_TO_A, pos2(293.0, 152.0)),
+        wire(matrix.send(0, 0), COLOR_A, &A_TO_A, pos2(20.0, 168.0)),
+        wire(matrix.send(1, 1), COLOR_B, &B_TO_B, pos2(460.0, 168.0)),
+        wire(&params.line_a.level, COLOR_A, &A_TO_OUT, A_TO_OUT[1]),
+        wire(&params.line_b.level, COLOR_B, &B_TO_OUT, B_TO_OUT[1]),
+    ]
+}
+
+pub fn signal_flow(ui: &mut egui::Ui, setter: &ParamSetter, params: &DParams) {
+    let scale = (ui.available_width() / SCHEME_SIZE.x).min(MAX_SCALE);
+    let (response, painter) = ui.allocate_painter(SCHEME_SIZE * scale, Sense::click_and_drag());
+    let origin = response.rect.min;
+    let to_screen = |point: Pos2| origin + point.to_vec2() * scale;
+    let to_scheme = |point: Pos2| pos2(0.0, 0.0) + (point - origin) / scale;
+    let background = ui.visuals().extreme_bg_color;
+
+    let controls = controls(params);
+    // ближайший к указателю элемент в пределах HIT_DISTANCE
+    let control_at = |point: Pos2| {
+        let point = to_scheme(point);
+        controls
+            .iter()
+            .map(|control| control.distance(point))
+            .enumerate()
+            .filter(|(_, distance)| *distance <= HIT_DISTANCE / scale)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(control_idx, _)| control_idx)
+    };
+
+    // Перетаскивание длится несколько кадров, элемент запоминается в памяти egui
+    let drag_id = response.id.with("drag");
+    let mut drag: Option<Drag> = ui.data(|data| data.get_temp(drag_id));
+    if response.drag_started()
+        && let Some(control_idx) = response.interact_pointer_pos().and_then(control_at)
+    {
+        let param = controls[control_idx].param;
+        setter.begin_set_parameter(param);
+        drag = Some(Drag {
+            control_idx,
+            normalized: param.unmodulated_normalized_value(),
+        });
+    }
+    if let Some(state) = &mut drag {
+        let param = controls[state.control_idx].param;
+        if response.dragged() {
+            let precision = if ui.input(|input| input.modifiers.shift) {
+                0.1
+            } else {
+                1.0
+            };
+            state.normalized = (state.normalized
+                - response.drag_delta().y * precision / (DRAG_RANGE * scale))
+                .clamp(0.0, 1.0);
+            setter.set_parameter_normalized(param, state.normalized);
+        }
+        if response.drag_stopped() {
+            setter.end_set_parameter(param);
+            drag = None;
+        }
+    }
+    match drag {
+        Some(state) => ui.data_mut(|data| data.insert_temp(drag_id, state)),
+        None => ui.data_mut(|data| data.remove::<Drag>(drag_id)),
+    }
+
+    let hovered = response.hover_pos().and_then(control_at);
+    if response.double_clicked()
+        && let Some(control_idx) = hovered
+    {
+        let param = controls[control_idx].param;
+        setter.begin_set_parameter(param);
+        setter.set_parameter(param, param.default_plain_value());
+        setter.end_set_parameter(param);
+    }
+    let active = drag.map(|state| state.control_idx).or(hovered);
+    if let Some(control_idx) = active {
+        let param = controls[control_idx].param;
+        ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeVertical);
+        response.on_hover_text_at_pointer(format!("{}: {}", param.name(), param));
+    }
+
+    // Сначала провода, поверх них узлы: концы проводов прячутся под узлами
+    for (control_idx, control) in controls.iter().enumerate() {
+        if let Element::Wire { points, .. } = control.shape {
+            let mut stroke = send_stroke(control.param, control.color, scale);
+            if active == Some(control_idx) {
+                stroke.width += 2.0 * scale;
+            }
+            let screen_points: Vec<Pos2> = points.iter().copied().map(to_screen).collect();
+            painter.add(Shape::line(screen_points.clone(), stroke));
+            if let [.., from, to] = screen_points[..] {
+                arrow(&painter, from, to, 6.0 * scale);
+            }
+        }
+    }
+
+    painter.add(Shape::convex_polygon(
+        INPUT.iter().copied().map(to_screen).collect(),
+        COLOR_IO,
+        Stroke::NONE,
+    ));
+    painter.text(
+        to_screen(pos2(240.0, 14.0)),
+        Align2::CENTER_CENTER,
+        "IN",
+        FontId::proportional(13.0 * scale),
+        Color32::BLACK,
+    );
+    painter.add(Shape::convex_polygon(
+        OUTPUT.iter().copied().map(to_screen).collect(),
+        COLOR_IO,
+        Stroke::NONE,
+    ));
+    painter.text(
+        to_screen(pos2(240.0, 385.0)),
+        Align2::CENTER_CENTER,
+        "OUT",
+        FontId::proportional(13.0 * scale),
+        Color32::BLACK,
+    );
+
+    for (control_idx, control) in controls.iter().enumerate() {
+        let width = if active == Some(control_idx) {
+            3.5
+        } else {
+            2.5
+        } * scale;
+        let normalized = control.param.modulated_normalized_value();
+        match control.shape {
+            Element::Wire {
+                knob, knob_radius, ..
+            } => {
+                let color = if control.param.value() < 0.0 {
+                    COLOR_NEGATIVE
+                } else {
+                    control.color
+                };
+                knob_shape(
+                    &painter,
+                    to_screen(knob),
+                    knob_radius * scale,
+                    normalized,
+                    background,
+                    Stroke::new(width, color),
+                );
+            }
+            Element::Line { rect, name } => {
+                let rect = Rect::from_min_max(to_screen(rect.min), to_screen(rect.max));
+                // как на схеме: округлый верх, почти прямоугольный низ
+                let top = (55.0 * scale) as u8;
+                let bottom = (14.0 * scale) as u8;
+                painter.rect(
+                    rect,
+                    CornerRadius {
+                        nw: top,
+                        ne: top,
+                        sw: bottom,
+                        se: bottom,
+                    },
+                    background,
+                    Stroke::new(width, control.color),
+                    StrokeKind::Inside,
+                );
+                painter.text(
+                    rect.center_top() + vec2(0.0, 22.0 * scale),
+                    Align2::CENTER_CENTER,
+                    name,
+                    FontId::proportional(18.0 * scale),
+                    control.color,
+                );
+                knob_shape(
+                    &painter,
+                    rect.center() + vec2(0.0, 6.0 * scale),
+                    34.0 * scale,
+                    normalized,
+                    background,
+                    Stroke::new(width, control.color),
+                );
+                painter.text(
+                    rect.center_bottom() - vec2(0.0, 16.0 * scale),
+                    Align2::CENTER_CENTER,
+                    control.param.to_string(),
+                    FontId::proportional(11.0 * scale),
+                    control.color,
+                );
+            }
+        }
+    }
+}
+
+// Толщина по модулю посыла, цвет по знаку; нулевой посыл - тонкий и тусклый
+fn send_stroke(param: &FloatParam, color: Color32, scale: f32) -> Stroke {
+    let amount = (param.value() / 100.0).clamp(-1.0, 1.0);
+    let color = if amount < 0.0 { COLOR_NEGATIVE } else { color };
+    Stroke::new(
+        (1.0 + 5.0 * amount.abs()) * scale,
+        color.gamma_multiply(0.3 + 0.7 * amount.abs()),
+    )
+}
+
+// Ручка: риска вверх - середина диапазона, по краям - ±135°
+fn knob_shape(
+    painter: &egui::Painter,
+    center: Pos2,
+    radius: f32,
+    normalized: f32,
+    fill: Color32,
+    stroke: Stroke,
+) {
+    painter.circle(center, radius, fill, stroke);
+    let angle = (normalized - 0.5) * 1.5 * PI;
+    painter.line_segment(
+        [center, center + vec2(angle.sin(), -angle.cos()) * radius],
+        stroke,
+    );
+}
+
+fn arrow(painter: &egui::Painter, from: Pos2, to: Pos2, size: f32) {
+    let direction = (to - from).normalized();
+    let normal = direction.rot90();
+    let center = from + (to - from) * 0.7;
+    painter.add(Shape::convex_polygon(
+        vec![
+            center + direction * size,
+            center - direction * size + normal * size * 0.8,
+            center - direction * size - normal * size * 0.8,
+        ],
+        COLOR_IO,
+        Stroke::NONE,
+    ));
+}
+
+fn distance_to_segment(point: Pos2, from: Pos2, to: Pos2) -> f32 {
+    let segment = to - from;
+    let t = ((point - from).dot(segment) / segment.length_sq().max(f32::EPSILON)).clamp(0.0, 1.0);
+    point.distance(from + segment * t)
+}
@@ -0,0 +1,294 @@
+// Поворотная ручка вместо widgets::ParamSlider: вертикальное перетаскивание (с Shift - точнее),
+// двойной щелчок - значение по умолчанию, колесо - шаги параметра, щелчок - ввод значения текстом.
+// Переключатели (два шага) по щелчку меняют значение
+use nih_plug::prelude::{Param, ParamSetter};
+use nih_plug_egui::egui::{
+    self, Align2, FontId, Key, Pos2, Response, Sense, Shape, Stroke, TextEdit, Ui, Vec2, Widget,
+    vec2,
+};
+use std::f32::consts::PI;
+use std::time::Duration;
+
+// угол от риски вверх до края шкалы
+const MAX_ANGLE: f32 = 0.75 * PI;
+const ARC_SEGMENTS: usize = 32;
+// перетаскивание на столько точек проходит весь диапазон, с Shift - в 10 раз точнее
+const DRAG_RANGE: f32 = 200.0;
+const TEXT_HEIGHT: f32 = 14.0;
+
+pub struct Knob<'a, P: Param> {
+    param: &'a P,
+    setter: &'a ParamSetter<'a>,
+    label: Option<&'a str>,
+    diameter: f32,
+    width: f32,
+    // дуга от середины шкалы, для посылов ±100%
+    bipolar: bool,
+}
+
+// Нормированное значение во время перетаскивания, без округления до шагов параметра
+#[derive(Debug, Clone, Copy)]
+struct Drag(f32);
+
+// Текст, который пользователь вводит вместо значения
+#[derive(Debug, Clone)]
+struct Entry(String);
+
+// Время одиночного щелчка, который еще может оказаться первым щелчком двойного
+#[derive(Debug, Clone, Copy)]
+struct PendingClick(f64);
+
+impl<'a, P: Param> Knob<'a, P> {
+    pub fn for_param(param: &'a P, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            param,
+            setter,
+            label: None,
+            diameter: 36.0,
+            width: 72.0,
+            bipolar: false,
+        }
+    }
+
+    // Подпись над ручкой
+    pub fn with_label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn with_diameter(mut self, diameter: f32) -> Self {
+        self.diameter = diameter;
+        self.width = self.width.max(diameter);
+        self
+    }
+
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width.max(self.diameter);
+        self
+    }
+
+    pub fn bipolar(mut self) -> Self {
+        self.bipolar = true;
+        self
+    }
+
+    fn set_normalized(&self, normalized: f32) {
+        self.setter.begin_set_parameter(self.param);
+        self.setter
+            .set_parameter_normalized(self.param, normalized.clamp(0.0, 1.0));
+        self.setter.end_set_parameter(self.param);
+    }
+
+    fn handle_drag(&self, ui: &Ui, response: &Response) {
+        let drag_id = response.id.with("drag");
+        if response.drag_started() {
+            self.setter.begin_set_parameter(self.param);
+            let normalized = self.param.unmodulated_normalized_value();
+            ui.data_mut(|data| data.insert_temp(drag_id, Drag(normalized)));
+        }
+        if response.dragged() {
+            let precision = if ui.input(|input| input.modifiers.shift) {
+                0.1
+            } else {
+                1.0
+            };
+            let Drag(normalized) = ui
+                .data(|data| data.get_temp(drag_id))
+                .unwrap_or(Drag(self.param.unmodulated_normalized_value()));
+            let normalized =
+                (normalized - response.drag_delta().y * precision / DRAG_RANGE).clamp(0.0, 1.0);
+            ui.data_mut(|data| data.insert_temp(drag_id, Drag(normalized)));
+            self.setter.set_parameter_normalized(self.param, normalized);
+        }
+        if response.drag_stopped() {
+            self.setter.end_set_parameter(self.param);
+            ui.data_mut(|data| data.remove::<Drag>(drag_id));
+        }
+    }
+
+    fn handle_scroll(&self, ui: &Ui, response: &Response) {
+        if !response.hovered() {
+            return;
+        }
+        let scroll = ui.input(|input| input.raw_scroll_delta.y);
+        if scroll == 0.0 {
+            return;
+        }
+        let finer = ui.input(|input| input.modifiers.shift);
+        let current = self.param.unmodulated_normalized_value();
+        let normalized = if scroll > 0.0 {
+            self.param.next_normalized_step(current, finer)
+        } else {
+            self.param.previous_normalized_step(current, finer)
+        };
+        self.set_normalized(normalized);
+        // колесо над ручкой не прокручивает окно
+        ui.ctx().input_mut(|input| {
+            input.raw_scroll_delta = Vec2::ZERO;
+            input.smooth_scroll_delta = Vec2::ZERO;
+        });
+    }
+
+    // egui сообщает о первом щелчке двойного как об одиночном, поэтому одиночный щелчок
+    // выполняется только когда время на второй щелчок вышло, а двойной его отменяет
+    fn handle_click(&self, ui: &Ui, response: &Response, is_switch: bool) {
+        let pending_id = response.id.with("pending_click");
+        let now = ui.input(|input| input.time);
+        if response.double_clicked() {
+            ui.data_mut(|data| {
+                data.remove::<PendingClick>(pending_id);
+                data.remove::<Entry>(response.id.with("entry"));
+            });
+            self.set_normalized(self.param.default_normalized_value());
+            return;
+        }
+        if response.clicked() {
+            ui.data_mut(|data| data.insert_temp(pending_id, PendingClick(now)));
+        }
+
+        let Some(PendingClick(clicked_at)) = ui.data(|data| data.get_temp(pending_id)) else {
+            return;
+        };
+        let delay = ui
+            .ctx()
+            .options(|options| options.input_options.max_double_click_delay);
+        if now - clicked_at < delay {
+            ui.ctx()
+                .request_repaint_after(Duration::from_secs_f64(delay - (now - clicked_at)));
+            return;
+        }
+        ui.data_mut(|data| data.remove::<PendingClick>(pending_id));
+        if is_switch {
+            let normalized = self.param.unmodulated_normalized_value();
+            self.set_normalized(1.0 - normalized.round());
+        } else {
+            let text = self
+                .param
+                .normalized_value_to_string(self.param.unmodulated_normalized_value(), false);
+            ui.data_mut(|data| data.insert_temp(response.id.with("entry"), Entry(text)));
+        }
+    }
+
+    // Поле ввода на месте значения. Enter или потеря фокуса применяют текст, Escape отменяет
+    fn text_entry(&self, ui: &mut Ui, response: &Response, rect: egui::Rect) -> bool {
+        let entry_id = response.id.with("entry");
+        let Some(Entry(mut text)) = ui.data(|data| data.get_temp(entry_id)) else {
+            return false;
+        };
+        let text_id = entry_id.with("text");
+        let edit = ui.put(
+            rect,
+            TextEdit::singleline(&mut text)
+                .id(text_id)
+                .font(FontId::proportional(11.0))
+                .horizontal_align(egui::Align::Center),
+        );
+        if !edit.has_focus() && !edit.lost_focus() {
+            edit.request_focus();
+        }
+
+        if ui.input(|input| input.key_pressed(Key::Escape)) {
+            ui.data_mut(|data| data.remove::<Entry>(entry_id));
+        } else if edit.lost_focus() {
+            if let Some(normalized) = self.param.string_to_normalized_value(&text) {
+                self.set_normalized(normalized);
+            }
+            ui.data_mut(|data| data.remove::<Entry>(entry_id));
+        } else {
+            ui.data_mut(|data| data.insert_temp(entry_id, Entry(text)));
+        }
+        true
+    }
+}
+
+impl<P: Param> Widget for Knob<'_, P> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let label_height = if self.label.is_some() {
+            TEXT_HEIGHT
+        } else {
+            0.0
+        };
+        let size = vec2(self.width, label_height + self.diameter + TEXT_HEIGHT + 4.0);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+        let is_switch = self.param.step_count() == Some(1);
+
+        self.handle_drag(ui, &response);
+        self.handle_scroll(ui, &response);
+        self.handle_click(ui, &response, is_switch);
+
+        let visuals = *ui.style().interact(&response);
+        let color = ui.visuals().selection.bg_fill;
+        let center = rect.center_top() + vec2(0.0, label_height + self.diameter / 2.0 + 2.0);
+        let radius = self.diameter / 2.0;
+        let painter = ui.painter_at(rect.expand(2.0));
+
+        if let Some(label) = self.label {
+            painter.text(
+                rect.center_top(),
+                Align2::CENTER_TOP,
+                label,
+                FontId::proportional(11.0),
+                ui.visuals().text_color(),
+            );
+        }
+
+        // Шкала, дуга от начала шкалы (или от середины для посылов) до значения и риска
+        let normalized = self.param.modulated_normalized_value();
+        let angle = |normalized: f32| (normalized * 2.0 - 1.0) * MAX_ANGLE;
+        painter.circle_filled(center, radius, visuals.bg_fill);
+        painter.add(arc(
+            center,
+            radius,
+            -MAX_ANGLE,
+            MAX_ANGLE,
+            Stroke::new(3.0, ui.visuals().widgets.noninteractive.bg_stroke.color),
+        ));
+        let start = if self.bipolar { 0.0 } else { -MAX_ANGLE };
+        painter.add(arc(
+            center,
+            radius,
+            start,
+            angle(normalized),
+            Stroke::new(3.0, color),
+        ));
+        painter.line_segment(
+            [
+                center + direction(angle(normalized)) * radius * 0.3,
+                center + direction(angle(normalized)) * radius * 0.85,
+            ],
+            Stroke::new(2.0, visuals.fg_stroke.color),
+        );
+
+        let value_rect = egui::Rect::from_min_max(
+            rect.left_bottom() - vec2(0.0, TEXT_HEIGHT + 2.0),
+            rect.right_bottom(),
+        );
+        if !self.text_entry(ui, &response, value_rect) {
+            painter.text(
+                value_rect.center(),
+                Align2::CENTER_CENTER,
+                self.param.to_string(),
+                FontId::proportional(11.0),
+                visuals.text_color(),
+            );
+        }
+
+        response.on_hover_text(self.param.name())
+    }
+}
+
+// Направление риски: угол от вертикали по часовой стрелке
+fn direction(angle: f32) -> Vec2 {
+    vec2(angle.sin(), -angle.cos())
+}
+
+fn arc(center: Pos2, radius: f32, from: f32, to: f32, stroke: Stroke) -> Shape {
+    let (from, to) = if from <= to { (from, to) } else { (to, from) };
+    let points = (0..=ARC_SEGMENTS)
+        .map(|segment_idx| {
+            let angle = from + (to - from) * segment_idx as f32 / ARC_SEGMENTS as f32;
+            center + direction(angle) * radius
+        })
+        .collect();
+    Shape::line(points, stroke)
+}
@@ -1,193 +1,108 @@
+use atomic_float::AtomicF32;
 use nih_plug::plugin::vst3::Vst3Plugin;
 use nih_plug::prelude::*;
 use nih_plug::wrapper::vst3::subcategories::Vst3SubCategory;
-use nih_plug_egui::{EguiState, create_egui_editor, egui, widgets};
+use nih_plug_egui::{EguiState, create_egui_editor, egui};
 use std::sync::Arc;
-
-mod delay_line;
-mod utils;
-
-#[derive(Params)]
-struct DParams {
-    #[id = "in_send_out"]
-    pub in_send_out: FloatParam,
-    #[id = "in_send_a"]
-    pub in_send_a: FloatParam,
-    #[id = "in_send_b"]
-    pub in_send_b: FloatParam,
-
-    #[id = "line_a_level"]
-    pub a_send_out: FloatParam,
-    #[id = "line_a_delay"]
-    pub delay_a: FloatParam,
-    #[id = "line_a_feedback"]
-    pub fb_a: FloatParam,
-    #[id = "a_to_b_send"]
-    pub a_send_b: FloatParam,
-
-    #[id = "line_b_level"]
-    pub b_send_out: FloatParam,
-    #[id = "line_b_delay"]
-    pub delay_b: FloatParam,
-    #[id = "line_b_feedback"]
-    pub fb_b: FloatParam,
-    #[id = "b_to_a_send"]
-    pub b_send_a: FloatParam,
-}
-
-const MIN_DELAY_TIME: f32 = 0.025; // milliseconds
-const MAX_DELAY_TIME: f32 = 16000.0;
-
-impl Default for DParams {
-    fn default() -> Self {
-        Self {
-            in_send_out: FloatParam::new(
-                "Dry Level",
-                100.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            in_send_a: FloatParam::new(
-                "Input to A",
-                100.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            in_send_b: FloatParam::new(
-                "Input to B",
-                100.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            a_send_out: FloatParam::new(
-                "A to out",
-                0.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            b_send_out: FloatParam::new(
-                "B to out",
-                0.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            a_send_b: FloatParam::new(
-                "A to B",
-                0.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            b_send_a: FloatParam::new(
-                "B to A",
-                0.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-
-            fb_a: FloatParam::new(
-                "Feedback A",
-                0.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-            fb_b: FloatParam::new(
-                "Feedback B",
-                0.0,
-                FloatRange::Linear {
-                    min: -100.0,
-                    max: 100.0,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.2}%", s))),
-
-            delay_a: FloatParam::new(
-                "Delay A",
-                500.0,
-                FloatRange::Linear {
-                    min: MIN_DELAY_TIME,
-                    max: MAX_DELAY_TIME,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.3} ms", s))),
-            delay_b: FloatParam::new(
-                "Delay B",
-                500.0,
-                FloatRange::Linear {
-                    min: MIN_DELAY_TIME,
-                    max: MAX_DELAY_TIME,
-                },
-            )
-            .with_value_to_string(Arc::new(|s| format!("{:.3} ms", s))),
-        }
-    }
-}
-struct Delay {
+use std::sync::atomic::Ordering;
+
+mod graph;
+mod knob;
+mod meters;
+pub mod params;
+pub mod preset;
+mod response;
+mod scope;
+#[cfg(feature = "standalone")]
+pub mod standalone;
+#[cfg(any(feature = "standalone", feature = "render"))]
+pub mod wav;
+
+// вся обработка звука живет в отдельном крейте без зависимостей от nih_plug и egui
+pub use microdelay_engine as engine;
+
+use engine::tempo_sync;
+use engine::voices::{MAX_VOICES, VoiceTarget};
+use engine::{MAX_CHANNELS, MatrixDelayEngine};
+use graph::{COLOR_A, COLOR_B};
+use knob::Knob;
+use meters::{MeterLevels, MeterProbe};
+use params::{DParams, DriveCurve, LINE_NAMES, LineParams, MIN_DELAY_TIME};
+use preset::Preset;
+use response::{HostInfo, ResponseWorker};
+use scope::{ScopeProbe, ScopeSnapshot};
+
+pub struct Delay {
     params: Arc<DParams>,
-    samplerate: f32,
-
-    line_a: delay_line::DelayLine,
-    line_b: delay_line::DelayLine,
-
-    in_send_a_automation_samples: Vec<f32>,
-    in_send_b_automation_samples: Vec<f32>,
-
-    a_send_out_automation_samples: Vec<f32>,
-    b_send_out_automation_samples: Vec<f32>,
-    dry_automation_samples: Vec<f32>,
-
-    a_send_b_automation_samples: Vec<f32>,
-    b_send_a_automation_samples: Vec<f32>,
+    engine: MatrixDelayEngine,
+    // спектральный радиус матрицы посылов, для индикатора в редакторе
+    loop_gain: Arc<AtomicF32>,
+    // уровни входа, линий A и B и выхода для индикаторов
+    meter_levels: Arc<MeterLevels>,
+    meter_probe: MeterProbe,
+    // частота и транспорт для расчета отклика в редакторе
+    host_info: Arc<HostInfo>,
+    // прореженные буферы линий для осциллографа
+    scope_snapshot: Arc<ScopeSnapshot>,
+    scope_probe: ScopeProbe,
+    // ID голоса хоста и MIDI-канал звучащих нот: по ним приходит полифоническая модуляция
+    host_voices: [Option<(i32, u8)>; 128],
 
     editor_state: Arc<EguiState>,
+
+    // позиция в звуковом файле, который standalone-приложение подает на вход
+    #[cfg(feature = "standalone")]
+    input_file_position: usize,
 }
 
 impl Default for Delay {
     fn default() -> Self {
         Self {
             params: Default::default(),
-            samplerate: Default::default(),
-
-            in_send_a_automation_samples: Default::default(),
-            in_send_b_automation_samples: Default::default(),
-            a_send_out_automation_samples: Default::default(),
-            b_send_out_automation_samples: Default::default(),
-            dry_automation_samples: Default::default(),
-
-            a_send_b_automation_samples: Default::default(),
-            b_send_a_automation_samples: Default::default(),
-
-            line_a: Default::default(),
-            line_b: Default::default(),
-
-            editor_state: EguiState::from_size(740, 435),
+            engine: Default::default(),
+            loop_gain: Arc::new(AtomicF32::new(0.0)),
+            meter_levels: Default::default(),
+            meter_probe: Default::default(),
+            host_info: Default::default(),
+            scope_snapshot: Default::default(),
+            scope_probe: Default::default(),
+            host_voices: [None; 128],
+
+            editor_state: EguiState::from_size(740, 760),
+
+            #[cfg(feature = "standalone")]
+            input_file_position: 0,
         }
     }
 }
 
+impl Delay {
+    // Полифоническая модуляция CLAP: смещение нормированного значения параметра для одной ноты
+    fn modulate_voice(&mut self, voice_id: i32, poly_modulation_id: u32, normalized_offset: f32) {
+        let Some(note) = self
+            .host_voices
+            .iter()
+            .position(|voice| voice.is_some_and(|(id, _)| id == voice_id))
+        else {
+            return;
+        };
+        let tuning = &self.params.tuning;
+        let (target, param) = match poly_modulation_id {
+            params::FINE_TUNE_POLY_MOD_ID => (VoiceTarget::FineTune, &tuning.fine_tune),
+            params::EXCITATION_POLY_MOD_ID => (VoiceTarget::Excitation, &tuning.excitation),
+            _ => return,
+        };
+        // обе шкалы линейные: смещение в центах и процентах не зависит от общего значения
+        let offset = param.preview_modulated(normalized_offset) - param.unmodulated_plain_value();
+        self.engine.modulate_voice(note as u8, target, offset);
+    }
+}
+
+// ID голоса для хостов, которые не присылают свой, как в примерах nih_plug
+fn fallback_voice_id(channel: u8, note: u8) -> i32 {
+    note as i32 | ((channel as i32) << 16)
+}
+
 impl Plugin for Delay {
     type SysExMessage = ();
     type BackgroundTask = ();
@@ -215,7 +130,8 @@ impl Plugin for Delay {
         },
     ];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    // ноты настраивают линии A и B, см. TuningParams
+    const MIDI_INPUT: MidiConfig = MidiConfig::Basic;
     const MIDI_OUTPUT: MidiConfig = MidiConfig::None;
 
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
@@ -230,297 +146,453 @@ impl Plugin for Delay {
         buffer_config: &BufferConfig,
         _context: &mut impl InitContext<Self>,
     ) -> bool {
-        self.samplerate = buffer_config.sample_rate;
         let num_channels = audio_io_layout
             .main_output_channels
             .map(|n| n.get())
-            .unwrap_or(0);
-
-        self.line_a.init(
-            (self.samplerate * MAX_DELAY_TIME / 1e3) as usize + 5,
-            num_channels as usize,
-            buffer_config.max_buffer_size as usize,
-            self.samplerate,
-        );
-
-        self.line_b.init(
-            (self.samplerate * MAX_DELAY_TIME / 1e3) as usize + 5,
-            num_channels as usize,
+            .unwrap_or(0) as usize;
+        self.engine.init(
+            num_channels,
             buffer_config.max_buffer_size as usize,
-            self.samplerate,
+            buffer_config.sample_rate,
         );
-
-        self.in_send_a_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
-        self.in_send_b_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
-        self.a_send_out_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
-        self.b_send_out_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
-        self.dry_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
-        self.a_send_b_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
-        self.b_send_a_automation_samples = vec![0.0; buffer_config.max_buffer_size as usize];
+        self.meter_probe.init(buffer_config.sample_rate);
+        self.host_info.set_samplerate(buffer_config.sample_rate);
+        self.scope_probe.init(buffer_config.sample_rate);
 
         true
     }
 
     fn reset(&mut self) {
-        self.line_a.reset();
-        self.line_b.reset();
+        self.engine.reset();
+        self.meter_probe.reset();
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let block_len = buffer.samples();
-        // заполнение автоматизации
+        #[cfg(feature = "standalone")]
         {
-            self.params
-                .a_send_b
-                .smoothed
-                .next_block(&mut self.a_send_b_automation_samples, block_len);
-            self.params
-                .a_send_out
-                .smoothed
-                .next_block(&mut self.a_send_out_automation_samples, block_len);
-            self.params
-                .b_send_a
-                .smoothed
-                .next_block(&mut self.b_send_a_automation_samples, block_len);
-            self.params
-                .b_send_out
-                .smoothed
-                .next_block(&mut self.b_send_out_automation_samples, block_len);
-            self.params
-                .delay_a
-                .smoothed
-                .next_block(&mut self.line_a.delay_automation_samples, block_len);
-            self.params
-                .delay_b
-                .smoothed
-                .next_block(&mut self.line_b.delay_automation_samples, block_len);
-            self.params
-                .fb_a
-                .smoothed
-                .next_block(&mut self.line_a.feedback_automation_samples, block_len);
-            self.params
-                .fb_b
-                .smoothed
-                .next_block(&mut self.line_b.feedback_automation_samples, block_len);
-            self.params
-                .in_send_a
-                .smoothed
-                .next_block(&mut self.in_send_a_automation_samples, block_len);
-            self.params
-                .in_send_b
-                .smoothed
-                .next_block(&mut self.in_send_b_automation_samples, block_len);
-            self.params
-                .in_send_out
-                .smoothed
-                .next_block(&mut self.dry_automation_samples, block_len);
-
-            self.a_send_b_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.a_send_out_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.b_send_a_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.b_send_out_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.line_a
-                .feedback_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.line_b
-                .feedback_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.in_send_a_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.in_send_b_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
-            self.dry_automation_samples
-                .iter_mut()
-                .for_each(|s| *s = utils::knob_gain(*s));
+            standalone::mark_running();
+            standalone::fill_input(buffer, &mut self.input_file_position);
+        }
 
-            self.line_a
-                .delay_automation_samples
+        let transport = context.transport();
+        let engine_params = self.params.engine_params(tempo_sync::TransportInfo {
+            tempo: transport.tempo,
+            time_signature: transport
+                .time_sig_numerator
+                .zip(transport.time_sig_denominator),
+        });
+        self.engine
+            .prepare_block(buffer.channels(), buffer.samples(), &engine_params);
+        self.host_info.set_transport(&engine_params.transport);
+        let mut next_event = context.next_event();
+        // Все каналы одного сэмпла вместе: линии пишут выходы соседнего канала
+        for (sample_idx, mut channel_samples) in buffer.iter_samples().enumerate() {
+            // ноты - с точностью до сэмпла
+            while let Some(event) = next_event {
+                if event.timing() > sample_idx as u32 {
+                    break;
+                }
+                match event {
+                    NoteEvent::NoteOn {
+                        voice_id,
+                        channel,
+                        note,
+                        velocity,
+                        ..
+                    } => {
+                        let voice_id = voice_id.unwrap_or(fallback_voice_id(channel, note));
+                        self.host_voices[note as usize] = Some((voice_id, channel));
+                        self.engine.note_on(note, velocity);
+                    }
+                    NoteEvent::NoteOff { note, .. } => self.engine.note_off(note),
+                    NoteEvent::PolyModulation {
+                        voice_id,
+                        poly_modulation_id,
+                        normalized_offset,
+                        ..
+                    } => self.modulate_voice(voice_id, poly_modulation_id, normalized_offset),
+                    // MonoAutomation не нужен: голоса хранят только смещения от общего значения
+                    _ => (),
+                }
+                next_event = context.next_event();
+            }
+
+            let mut frame = [0.0; MAX_CHANNELS];
+            let frame = &mut frame[..channel_samples.len()];
+            frame
                 .iter_mut()
-                .for_each(|s| *s = self.samplerate * *s / 1e3);
-            self.line_b
-                .delay_automation_samples
+                .zip(channel_samples.iter_mut())
+                .for_each(|(value, sample)| *value = *sample);
+            self.meter_probe.add_frame(meters::INPUT_METER, frame);
+            self.engine.process_frame(frame, sample_idx);
+            self.meter_probe.add_frame(meters::OUTPUT_METER, frame);
+            for (line_idx, meter_idx) in meters::LINE_METERS.into_iter().enumerate() {
+                let line_frame: [f32; MAX_CHANNELS] = std::array::from_fn(|channel_idx| {
+                    self.engine.line_output(line_idx, channel_idx)
+                });
+                self.meter_probe
+                    .add_frame(meter_idx, &line_frame[..frame.len()]);
+            }
+            channel_samples
                 .iter_mut()
-                .for_each(|s| *s = self.samplerate * *s / 1e3);
+                .zip(frame.iter())
+                .for_each(|(sample, value)| *sample = *value);
         }
-
-        for (channel_idx, samples) in buffer.as_slice().iter_mut().enumerate() {
-            for (sample_idx, sample) in samples.iter_mut().enumerate() {
-                self.line_a
-                    .set_delay(self.line_a.delay_automation_samples[sample_idx]);
-                self.line_b
-                    .set_delay(self.line_b.delay_automation_samples[sample_idx]);
-
-                let value_to_play_a = self.line_a.read_value_from_channel(channel_idx);
-                let value_to_play_b = self.line_b.read_value_from_channel(channel_idx);
-
-                // Внутри цикла по сэмплам:
-                self.line_a.write_value_to_channel(
-                    // Умножаем входной сигнал на параметр посыла
-                    (*sample * self.in_send_a_automation_samples[sample_idx])
-                        + (value_to_play_b * self.b_send_a_automation_samples[sample_idx])
-                        + (value_to_play_a * self.line_a.feedback_automation_samples[sample_idx]),
-                    channel_idx,
-                );
-
-                self.line_b.write_value_to_channel(
-                    // Аналогично для линии B
-                    (*sample * self.in_send_b_automation_samples[sample_idx])
-                        + (value_to_play_a * self.a_send_b_automation_samples[sample_idx])
-                        + (value_to_play_b * self.line_b.feedback_automation_samples[sample_idx]),
-                    channel_idx,
-                );
-
-                // Вычисление компонент
-                let dry_component = *sample * self.dry_automation_samples[sample_idx];
-                let wet_component = value_to_play_a
-                    * self.a_send_out_automation_samples[sample_idx]
-                    + value_to_play_b * self.b_send_out_automation_samples[sample_idx];
-
-                // Смешивание
-                *sample = dry_component + wet_component;
-
-                // сдвиг каретки
-                self.line_a.move_arrow_over_channel(channel_idx);
-                self.line_b.move_arrow_over_channel(channel_idx);
+        // хост держит модуляцию голоса, пока не узнает, что тот замолчал
+        let timing = buffer.samples().saturating_sub(1) as u32;
+        for note in self.engine.ended_notes() {
+            if let Some((voice_id, channel)) = self.host_voices[note as usize].take() {
+                context.send_event(NoteEvent::VoiceTerminated {
+                    timing,
+                    voice_id: Some(voice_id),
+                    channel,
+                    note,
+                });
             }
         }
+        self.loop_gain
+            .store(self.engine.loop_gain(), Ordering::Relaxed);
+        self.meter_probe.publish(&self.meter_levels);
+        self.scope_probe.update(
+            &self.scope_snapshot,
+            &self.engine,
+            engine_params.line_count,
+            buffer.samples(),
+            self.editor_state.is_open(),
+        );
 
         ProcessStatus::Normal
     }
 
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
-        let params = self.params.clone();
+        #[cfg(feature = "standalone")]
+        if standalone::is_headless() {
+            return None;
+        }
 
-        const COLOR_A: egui::Color32 = egui::Color32::from_rgb(150, 255, 0); // Ярко-зеленый
-        const COLOR_B: egui::Color32 = egui::Color32::from_rgb(150, 0, 255); // Фиолетовый
-        const COLOR_DRY: egui::Color32 = egui::Color32::from_rgb(0, 255, 255); // Циан (центр)
+        let params = self.params.clone();
+        let loop_gain = self.loop_gain.clone();
+        let meter_levels = self.meter_levels.clone();
+        let host_info = self.host_info.clone();
+        let response_worker = ResponseWorker::default();
+        let scope_snapshot = self.scope_snapshot.clone();
 
         create_egui_editor(
             self.editor_state.clone(),
-            (),
-            |_ctx, _data| {},
-            move |egui_ctx, setter, _data| {
+            PresetBrowser::default(),
+            |_ctx, _browser| {},
+            move |egui_ctx, setter, browser| {
                 egui::CentralPanel::default().show(egui_ctx, |ui| {
                     ui.vertical_centered(|ui| {
                         ui.heading(egui::RichText::new("MICRODELAY MATRIX").strong().size(20.0));
                     });
-                    ui.add_space(15.0);
-
-                    // Используем сетку, чтобы повторить топологию Delay.png
-                    egui::Grid::new("delay_matrix_grid")
-                        .spacing([60.0, 20.0])
-                        .min_col_width(120.0)
-                        .show(ui, |ui| {
-                            // --- РЯД 1: Входные посылы (Верхние крутилки на схеме) ---
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new("INPUT -> A").color(COLOR_A));
-                                ui.add(widgets::ParamSlider::for_param(&params.in_send_a, setter));
+                    ui.add_space(5.0);
+                    preset_bar(ui, setter, &params, browser);
+                    ui.add_space(10.0);
+
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        // Схема сигнала как на Delay.png: посылы тянутся прямо по проводам
+                        ui.vertical_centered(|ui| {
+                            graph::signal_flow(ui, setter, &params);
+                        });
+                        ui.add_space(10.0);
+                        meters::meters_panel(ui, &meter_levels);
+                        ui.add_space(10.0);
+                        egui::CollapsingHeader::new("RESPONSE")
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                response::response_panel(
+                                    ui,
+                                    &response_worker,
+                                    params.engine_params(host_info.transport()),
+                                    host_info.samplerate(),
+                                );
                             });
+                        egui::CollapsingHeader::new("SCOPE").show(ui, |ui| {
+                            let line_count = params.line_count.value().count();
+                            scope::scope_panel(
+                                ui,
+                                &scope_snapshot,
+                                &LINE_NAMES[..line_count],
+                                &[COLOR_A, COLOR_B],
+                            );
+                        });
+                        ui.columns(2, |columns| {
+                            for (line_idx, (ui, color)) in
+                                columns.iter_mut().zip([COLOR_A, COLOR_B]).enumerate()
+                            {
+                                ui.label(
+                                    egui::RichText::new(format!("LINE {}", LINE_NAMES[line_idx]))
+                                        .strong()
+                                        .color(color),
+                                );
+                                line_controls(
+                                    ui,
+                                    setter,
+                                    params.line(line_idx),
+                                    host_info.samplerate(),
+                                );
+                            }
+                        });
 
-                            // Пустое место над Dry
-                            ui.label("");
-
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new("INPUT -> B").color(COLOR_B));
-                                ui.add(widgets::ParamSlider::for_param(&params.in_send_b, setter));
-                            });
-                            ui.end_row();
+                        // --- Дополнительные линии и полная матрица посылов ---
+                        ui.add_space(15.0);
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("LINES").strong());
+                            ui.add(Knob::for_param(&params.line_count, setter));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("SAFETY").strong());
+                            ui.add(Knob::for_param(&params.safety, setter));
+                            loop_gain_indicator(ui, loop_gain.load(Ordering::Relaxed));
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new("STEREO").strong());
+                            ui.add(Knob::for_param(&params.stereo_mode, setter));
+                            ui.add(Knob::for_param(&params.width, setter).with_label("Width"));
+                        });
+                        egui::CollapsingHeader::new("NOTE TUNING").show(ui, |ui| {
+                            tuning_controls(ui, setter, &params, host_info.samplerate());
+                        });
+                        let line_count = params.line_count.value().count();
 
-                            // --- РЯД 2: Кросс-фидбек A -> B (Верхняя горизонтальная линия) ---
-                            ui.label("");
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new("A -> B").color(COLOR_A));
-                                ui.add(widgets::ParamSlider::for_param(&params.a_send_b, setter));
-                            });
-                            ui.label("");
-                            ui.end_row();
-
-                            // --- РЯД 3: Основные блоки задержки и Dry (Центр схемы) ---
-                            // Слева: Блок A
-                            ui.vertical_centered(|ui| {
-                                ui.group(|ui| {
-                                    ui.label(egui::RichText::new("LINE A").strong().color(COLOR_A));
-                                    ui.label("Time (ms)");
-                                    ui.add(widgets::ParamSlider::for_param(
-                                        &params.delay_a,
+                        egui::CollapsingHeader::new("SEND MATRIX").show(ui, |ui| {
+                            send_matrix_grid(ui, setter, &params, line_count);
+                        });
+                        for (line_idx, line_name) in
+                            LINE_NAMES.iter().enumerate().take(line_count).skip(2)
+                        {
+                            egui::CollapsingHeader::new(format!("LINE {line_name}")).show(
+                                ui,
+                                |ui| {
+                                    line_controls(
+                                        ui,
                                         setter,
-                                    ));
-                                    ui.label("Local FB");
-                                    ui.add(widgets::ParamSlider::for_param(&params.fb_a, setter));
-                                });
-                            });
+                                        params.line(line_idx),
+                                        host_info.samplerate(),
+                                    );
+                                },
+                            );
+                        }
+                    });
+                });
+            },
+        )
+    }
+}
 
-                            // В центре: Dry Level
-                            ui.vertical_centered(|ui| {
-                                ui.add_space(20.0);
-                                ui.label(egui::RichText::new("IN -> OUT").color(COLOR_DRY));
-                                ui.add(widgets::ParamSlider::for_param(
-                                    &params.in_send_out,
-                                    setter,
-                                ));
-                            });
+// Спектральный радиус матрицы посылов: от единицы и выше петля не затухает
+fn loop_gain_indicator(ui: &mut egui::Ui, loop_gain: f32) {
+    let text = format!("LOOP GAIN {:.2}", loop_gain);
+    if loop_gain >= 1.0 {
+        ui.label(
+            egui::RichText::new(format!("{text} RUNAWAY"))
+                .strong()
+                .color(egui::Color32::RED),
+        );
+    } else {
+        ui.label(text);
+    }
+}
 
-                            // Справа: Блок B
-                            ui.vertical_centered(|ui| {
-                                ui.group(|ui| {
-                                    ui.label(egui::RichText::new("LINE B").strong().color(COLOR_B));
-                                    ui.label("Time (ms)");
-                                    ui.add(widgets::ParamSlider::for_param(
-                                        &params.delay_b,
-                                        setter,
-                                    ));
-                                    ui.label("Local FB");
-                                    ui.add(widgets::ParamSlider::for_param(&params.fb_b, setter));
-                                });
-                            });
-                            ui.end_row();
+// Состояние строки пресетов в редакторе
+struct PresetBrowser {
+    factory: Vec<Preset>,
+    selected: Option<usize>,
+    path: String,
+    // результат последней загрузки/сохранения
+    status: Result<String, String>,
+}
 
-                            // --- РЯД 4: Кросс-фидбек B -> A (Нижняя горизонтальная линия) ---
-                            ui.label("");
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new("B -> A").color(COLOR_B));
-                                ui.add(widgets::ParamSlider::for_param(&params.b_send_a, setter));
-                            });
-                            ui.label("");
-                            ui.end_row();
+impl Default for PresetBrowser {
+    fn default() -> Self {
+        // без диалога выбора файла: по умолчанию пресет лежит в домашнем каталоге
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+        let path = home
+            .map(std::path::PathBuf::from)
+            .unwrap_or_default()
+            .join(format!("microdelay_preset.{}", preset::PRESET_EXTENSION));
 
-                            // --- РЯД 5: Выходы в мастер (Нижние крутилки на схеме) ---
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new("A -> OUT").color(COLOR_A));
-                                ui.add(widgets::ParamSlider::for_param(&params.a_send_out, setter));
-                            });
+        Self {
+            factory: preset::factory_bank(),
+            selected: None,
+            path: path.to_string_lossy().into_owned(),
+            status: Ok(String::new()),
+        }
+    }
+}
 
-                            ui.label(""); // Точка суммирования
+fn preset_bar(
+    ui: &mut egui::Ui,
+    setter: &ParamSetter,
+    params: &DParams,
+    browser: &mut PresetBrowser,
+) {
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new("PRESET").strong());
+
+        let selected_name = browser
+            .selected
+            .map_or("Factory...", |idx| browser.factory[idx].name.as_str());
+        let mut selected = browser.selected;
+        egui::ComboBox::from_id_salt("factory_presets")
+            .selected_text(selected_name)
+            .show_ui(ui, |ui| {
+                for (idx, preset) in browser.factory.iter().enumerate() {
+                    ui.selectable_value(&mut selected, Some(idx), &preset.name)
+                        .on_hover_text(preset.tags.join(", "));
+                }
+            });
+        if selected != browser.selected {
+            browser.selected = selected;
+            if let Some(idx) = selected {
+                let preset = &browser.factory[idx];
+                browser.status = preset
+                    .load(params, setter)
+                    .map(|()| format!("Loaded {}", preset.name));
+            }
+        }
 
-                            ui.vertical_centered(|ui| {
-                                ui.label(egui::RichText::new("B -> OUT").color(COLOR_B));
-                                ui.add(widgets::ParamSlider::for_param(&params.b_send_out, setter));
-                            });
-                            ui.end_row();
-                        });
+        ui.add(egui::TextEdit::singleline(&mut browser.path).desired_width(300.0));
+        if ui.button("Load").clicked() {
+            browser.status = std::fs::read_to_string(&browser.path)
+                .map_err(|err| err.to_string())
+                .and_then(|json| Preset::from_json(&json))
+                .and_then(|preset| {
+                    preset.load(params, setter)?;
+                    Ok(format!("Loaded {}", preset.name))
                 });
-            },
-        )
+            browser.selected = None;
+        }
+        if ui.button("Save").clicked() {
+            let path = std::path::Path::new(&browser.path);
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let json = Preset::capture(&name, params).to_json();
+            browser.status = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::write(path, json))
+                .map(|()| format!("Saved {name}"))
+                .map_err(|err| err.to_string());
+        }
+    });
+
+    match &browser.status {
+        Ok(message) => ui.label(message.as_str()),
+        Err(err) => ui.label(egui::RichText::new(err.as_str()).color(egui::Color32::RED)),
+    };
+}
+
+// Таблица посылов: строки - источники (вход и линии), столбцы - линии-получатели и выход
+fn send_matrix_grid(ui: &mut egui::Ui, setter: &ParamSetter, params: &DParams, line_count: usize) {
+    egui::Grid::new("send_matrix_grid")
+        .spacing([8.0, 6.0])
+        .show(ui, |ui| {
+            ui.label("");
+            for to_name in &LINE_NAMES[..line_count] {
+                ui.label(egui::RichText::new(*to_name).strong());
+            }
+            ui.label(egui::RichText::new("OUT").strong());
+            ui.end_row();
+
+            ui.label("IN");
+            for input_send in &params.matrix.input[..line_count] {
+                ui.add(send_knob(input_send, setter));
+            }
+            ui.add(send_knob(&params.in_send_out, setter));
+            ui.end_row();
+
+            for (from_idx, from_name) in LINE_NAMES.iter().enumerate().take(line_count) {
+                ui.label(egui::RichText::new(*from_name).strong());
+                for to_idx in 0..line_count {
+                    ui.add(send_knob(params.matrix.send(from_idx, to_idx), setter));
+                }
+                ui.add(send_knob(&params.line(from_idx).level, setter));
+                ui.end_row();
+            }
+        });
+}
+
+// Все локальные параметры одной линии. Посылы и уровень линий A и B - на схеме сигнала
+fn line_controls(ui: &mut egui::Ui, setter: &ParamSetter, line: &LineParams, samplerate: f32) {
+    ui.horizontal_wrapped(|ui| {
+        param_knob(ui, "Time (ms)", &line.delay, setter);
+        // на низкой частоте или с насыщением линия короче этого не бывает
+        let min_time = line_min_time(line, samplerate);
+        if min_time > MIN_DELAY_TIME {
+            ui.label(egui::RichText::new(format!("min {min_time:.3} ms")).weak());
+        }
+        param_knob(ui, "Sync", &line.sync, setter);
+        param_knob(ui, "Division", &line.division, setter);
+        param_knob(ui, "Interpolation", &line.interpolation, setter);
+        param_knob(ui, "Pan", &line.pan, setter);
+        param_knob(ui, "Haas Offset", &line.stereo_offset, setter);
+        param_knob(ui, "Routing", &line.routing, setter);
+        param_knob(ui, "Swap Sends", &line.swap_sends, setter);
+        param_knob(ui, "M/S Channels", &line.mid_side, setter);
+        param_knob(ui, "LFO Shape", &line.lfo_shape, setter);
+        param_knob(ui, "LFO Rate", &line.lfo_rate, setter);
+        param_knob(ui, "LFO Depth", &line.lfo_depth, setter);
+        param_knob(ui, "LFO Stereo Phase", &line.lfo_stereo, setter);
+        param_knob(ui, "Low-pass", &line.lowpass, setter);
+        param_knob(ui, "High-pass", &line.highpass, setter);
+        param_knob(ui, "Resonance", &line.resonance, setter);
+        param_knob(ui, "Drive Curve", &line.drive_curve, setter);
+        param_knob(ui, "Drive", &line.drive, setter);
+        param_knob(ui, "Drive Bias", &line.drive_bias, setter);
+        param_knob(ui, "Drive Trim", &line.drive_trim, setter);
+    });
+}
+
+fn tuning_controls(ui: &mut egui::Ui, setter: &ParamSetter, params: &DParams, samplerate: f32) {
+    let tuning = &params.tuning;
+    ui.horizontal_wrapped(|ui| {
+        param_knob(ui, "Note Tuning", &tuning.enabled, setter);
+        param_knob(ui, "Fine Tune", &tuning.fine_tune, setter);
+        param_knob(ui, "Interval B", &tuning.interval_b, setter);
+        param_knob(ui, "Glide", &tuning.glide, setter);
+        param_knob(ui, "Excite", &tuning.excitation, setter);
+        param_knob(ui, "Voices", &tuning.voices, setter);
+        // выше этой высоты период короче самой короткой линии A или B
+        let min_time = line_min_time(params.line(0), samplerate)
+            .max(line_min_time(params.line(1), samplerate));
+        let max_pitch = 1e3 / min_time;
+        if max_pitch < samplerate / 2.0 {
+            ui.label(egui::RichText::new(format!("up to {max_pitch:.0} Hz")).weak());
+        }
+    });
+}
+
+// Самое короткое время линии при ее насыщении на частоте хоста
+fn line_min_time(line: &LineParams, samplerate: f32) -> f32 {
+    if samplerate > 0.0 {
+        engine::min_delay_time(samplerate, line.drive_curve.value() != DriveCurve::Off)
+    } else {
+        MIN_DELAY_TIME
     }
 }
 
+fn param_knob<P: Param>(ui: &mut egui::Ui, label: &str, param: &P, setter: &ParamSetter) {
+    ui.add(Knob::for_param(param, setter).with_label(label));
+}
+
+// Посылы ±100% в таблице: маленькая ручка с дугой от нуля
+fn send_knob<'a>(param: &'a FloatParam, setter: &'a ParamSetter<'a>) -> Knob<'a, FloatParam> {
+    Knob::for_param(param, setter)
+        .with_diameter(28.0)
+        .with_width(60.0)
+        .bipolar()
+}
+
 impl Vst3Plugin for Delay {
     const VST3_CLASS_ID: [u8; 16] = [
         98, 218, 94, 45, 78, 214, 174, 224, 167, 126, 143, 79, 37, 188, 235, 30,
@@ -528,4 +600,89 @@ impl Vst3Plugin for Delay {
     const VST3_SUBCATEGORIES: &'static [Vst3SubCategory] = &[Vst3SubCategory::Delay];
 }
 
+impl ClapPlugin for Delay {
+    const CLAP_ID: &'static str = "com.gema.microdelay";
+    const CLAP_DESCRIPTION: Option<&'static str> =
+        Some("Delay matrix with sends between delay lines");
+    const CLAP_MANUAL_URL: Option<&'static str> = Some(Self::URL);
+    const CLAP_SUPPORT_URL: Option<&'static str> = None;
+    const CLAP_FEATURES: &'static [ClapFeature] = &[
+        ClapFeature::AudioEffect,
+        ClapFeature::Delay,
+        ClapFeature::Stereo,
+        ClapFeature::Mono,
+    ];
+    // каждая нота получает свой голос, две ноты одной высоты голос не делят
+    const CLAP_POLY_MODULATION_CONFIG: Option<PolyModulationConfig> = Some(PolyModulationConfig {
+        max_voice_capacity: MAX_VOICES as u32,
+        supports_overlapping_voices: false,
+    });
+
+    // Страницы для аппаратных контроллеров (Bitwig, Reaper): до 8 ручек на страницу
+    fn remote_controls(&self, context: &mut impl RemoteControlsContext) {
+        let params = &self.params;
+        context.add_section("Matrix", |section| {
+            section.add_page("Sends", |page| {
+                page.add_param(&params.matrix.input[0]);
+                page.add_param(&params.matrix.input[1]);
+                page.add_param(params.matrix.send(0, 0));
+                page.add_param(params.matrix.send(0, 1));
+                page.add_param(params.matrix.send(1, 1));
+                page.add_param(params.matrix.send(1, 0));
+                page.add_param(&params.in_send_out);
+                page.add_param(&params.safety);
+            });
+            section.add_page("Stereo", |page| {
+                page.add_param(&params.stereo_mode);
+                page.add_param(&params.width);
+            });
+            section.add_page("Tuning", |page| {
+                page.add_param(&params.tuning.enabled);
+                page.add_param(&params.tuning.fine_tune);
+                page.add_param(&params.tuning.interval_b);
+                page.add_param(&params.tuning.glide);
+                page.add_param(&params.tuning.excitation);
+                page.add_param(&params.tuning.voices);
+            });
+        });
+        for (line_idx, line_name) in LINE_NAMES.iter().enumerate() {
+            let line = params.line(line_idx);
+            context.add_section(format!("Line {line_name}"), |section| {
+                section.add_page("Time", |page| {
+                    page.add_param(&line.delay);
+                    page.add_param(&line.sync);
+                    page.add_param(&line.division);
+                    page.add_param(&line.interpolation);
+                    page.add_param(&line.level);
+                    page.add_param(&line.pan);
+                    page.add_param(&line.stereo_offset);
+                    page.add_param(params.matrix.send(line_idx, line_idx));
+                });
+                section.add_page("LFO", |page| {
+                    page.add_param(&line.lfo_shape);
+                    page.add_param(&line.lfo_rate);
+                    page.add_param(&line.lfo_depth);
+                    page.add_param(&line.lfo_stereo);
+                });
+                section.add_page("Routing", |page| {
+                    page.add_param(&line.routing);
+                    page.add_param(&line.swap_sends);
+                    page.add_param(&line.mid_side);
+                });
+                section.add_page("Tone", |page| {
+                    page.add_param(&line.lowpass);
+                    page.add_param(&line.highpass);
+                    page.add_param(&line.resonance);
+                    page.add_spacer();
+                    page.add_param(&line.drive_curve);
+                    page.add_param(&line.drive);
+                    page.add_param(&line.drive_bias);
+                    page.add_param(&line.drive_trim);
+                });
+            });
+        }
+    }
+}
+
 nih_export_vst3!(Delay);
+nih_export_clap!(Delay);
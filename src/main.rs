@@ -0,0 +1,82 @@
+use microdelay::Delay;
+use microdelay::standalone;
+use microdelay::wav::AudioFile;
+use nih_plug::prelude::*;
+use std::time::Duration;
+
+const USAGE: &str = "\
+MicroDelay standalone options:
+      --input-wav <PATH>    Play a WAV file (looped) into the plugin input instead of the audio input
+      --headless            Run without the editor window
+      --run-for <SECONDS>   Run headless and stop after the given number of seconds of processing
+
+Use `--backend dummy` to run without audio hardware.
+";
+
+fn main() {
+    let mut args = std::env::args();
+    // остальные аргументы разбирает враппер nih_plug
+    let mut wrapper_args: Vec<String> = args.next().into_iter().collect();
+    let mut input_wav = None;
+    let mut run_for = None;
+    let mut samplerate_given = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input-wav" => input_wav = Some(expect_value(&mut args, &arg)),
+            "--headless" => standalone::set_headless(true),
+            "--run-for" => {
+                let seconds = expect_value(&mut args, &arg);
+                match seconds.parse::<f64>() {
+                    Ok(seconds) if seconds >= 0.0 => run_for = Some(seconds),
+                    _ => exit_with_error(&format!("invalid value for --run-for: {seconds}")),
+                }
+            }
+            _ => {
+                if arg == "-h" || arg == "--help" {
+                    println!("{USAGE}");
+                }
+                if arg == "-r" || arg.starts_with("--sample-rate") {
+                    samplerate_given = true;
+                }
+                wrapper_args.push(arg);
+            }
+        }
+    }
+
+    if let Some(path) = input_wav {
+        let file = AudioFile::read(&path)
+            .unwrap_or_else(|err| exit_with_error(&format!("can't read {path}: {err}")));
+        // без явной частоты дискретизации берем частоту файла, чтобы он не играл с другой скоростью
+        if !samplerate_given {
+            wrapper_args.push(String::from("--sample-rate"));
+            wrapper_args.push(file.samplerate.to_string());
+        }
+        standalone::set_input_file(file);
+    }
+
+    if let Some(seconds) = run_for {
+        // закрыть окно редактора изнутри плагина нельзя, поэтому остановка по времени - только без окна
+        #[cfg(unix)]
+        {
+            standalone::set_headless(true);
+            standalone::stop_after(Duration::from_secs_f64(seconds));
+        }
+        #[cfg(not(unix))]
+        exit_with_error("--run-for is only supported on Unix");
+    }
+
+    if !nih_export_standalone_with_args::<Delay, _>(wrapper_args) {
+        std::process::exit(1);
+    }
+}
+
+fn expect_value(args: &mut impl Iterator<Item = String>, flag: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| exit_with_error(&format!("{flag} requires a value")))
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {message}\n\n{USAGE}");
+    std::process::exit(1);
+}
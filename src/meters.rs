@@ -0,0 +1,204 @@
+// Индикаторы уровня входа, линий A и B и выхода. Аудиопоток копит пик и RMS и отдает их
+// редактору через атомики, редактор держит пик, дает ему спадать и запоминает перегрузку
+use atomic_float::AtomicF32;
+use nih_plug::prelude::util;
+use nih_plug_egui::egui::{self, Align2, Color32, FontId, Rect, Sense, Stroke, vec2};
+use std::sync::atomic::Ordering;
+
+pub const METER_COUNT: usize = 4;
+pub const METER_NAMES: [&str; METER_COUNT] = ["IN", "A", "B", "OUT"];
+pub const INPUT_METER: usize = 0;
+pub const LINE_METERS: [usize; 2] = [1, 2];
+pub const OUTPUT_METER: usize = 3;
+
+// время усреднения RMS
+const RMS_WINDOW: f32 = 300.0; // milliseconds
+const PEAK_HOLD: f64 = 1.5; // seconds
+const PEAK_FALL: f32 = 20.0; // dB per second
+const MIN_DB: f32 = -60.0;
+const MAX_DB: f32 = 6.0;
+const METER_HEIGHT: f32 = 12.0;
+
+// Уровни для редактора. Пик - максимум с прошлого кадра редактора, редактор забирает его
+// с обнулением; RMS - скользящее среднее на конец блока
+#[derive(Debug, Default)]
+pub struct MeterLevels {
+    peaks: [AtomicF32; METER_COUNT],
+    rms: [AtomicF32; METER_COUNT],
+}
+
+// Накопление уровней в аудиопотоке, без выделения памяти
+#[derive(Debug, Default)]
+pub struct MeterProbe {
+    rms_coeff: f32,
+    peaks: [f32; METER_COUNT],
+    mean_squares: [f32; METER_COUNT],
+}
+
+impl MeterProbe {
+    pub fn init(&mut self, samplerate: f32) {
+        self.rms_coeff = 1.0 - (-1e3 / (samplerate * RMS_WINDOW)).exp();
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.peaks = [0.0; METER_COUNT];
+        self.mean_squares = [0.0; METER_COUNT];
+    }
+
+    // Один сэмпл сигнала во всех каналах
+    #[inline]
+    pub fn add_frame(&mut self, meter_idx: usize, frame: &[f32]) {
+        let peak = frame
+            .iter()
+            .fold(0.0f32, |peak, value| peak.max(value.abs()));
+        let square =
+            frame.iter().map(|value| value * value).sum::<f32>() / frame.len().max(1) as f32;
+        self.peaks[meter_idx] = self.peaks[meter_idx].max(peak);
+        let mean_square = &mut self.mean_squares[meter_idx];
+        *mean_square += (square - *mean_square) * self.rms_coeff;
+        // после тишины среднее уходит в денормализованные числа
+        if *mean_square < 1e-20 {
+            *mean_square = 0.0;
+        }
+    }
+
+    // Конец блока: уровни уходят редактору
+    pub fn publish(&mut self, levels: &MeterLevels) {
+        for meter_idx in 0..METER_COUNT {
+            levels.peaks[meter_idx].fetch_max(self.peaks[meter_idx], Ordering::Relaxed);
+            levels.rms[meter_idx].store(self.mean_squares[meter_idx].sqrt(), Ordering::Relaxed);
+            self.peaks[meter_idx] = 0.0;
+        }
+    }
+}
+
+// То, что видно на индикаторе: пик со спадом, удержание пика и перегрузка до щелчка
+#[derive(Debug, Clone, Copy)]
+struct MeterDisplay {
+    peak_db: f32,
+    hold_db: f32,
+    hold_until: f64,
+    clipped: bool,
+}
+
+impl Default for MeterDisplay {
+    fn default() -> Self {
+        Self {
+            peak_db: MIN_DB,
+            hold_db: MIN_DB,
+            hold_until: 0.0,
+            clipped: false,
+        }
+    }
+}
+
+pub fn meters_panel(ui: &mut egui::Ui, levels: &MeterLevels) {
+    let display_id = ui.id().with("meters");
+    let mut displays: [MeterDisplay; METER_COUNT] = ui
+        .data(|data| data.get_temp(display_id))
+        .unwrap_or_default();
+    let (time, dt) = ui.input(|input| (input.time, input.stable_dt));
+
+    for (meter_idx, display) in displays.iter_mut().enumerate() {
+        let peak = levels.peaks[meter_idx].swap(0.0, Ordering::Relaxed);
+        let rms = levels.rms[meter_idx].load(Ordering::Relaxed);
+        let peak_db = util::gain_to_db(peak.max(1e-6));
+        let rms_db = util::gain_to_db(rms.max(1e-6));
+
+        display.peak_db = peak_db.max(display.peak_db - PEAK_FALL * dt).max(MIN_DB);
+        if display.peak_db >= display.hold_db || time > display.hold_until {
+            display.hold_db = display.peak_db;
+            display.hold_until = time + PEAK_HOLD;
+        }
+        display.clipped |= peak >= 1.0;
+
+        ui.horizontal(|ui| {
+            ui.add_sized(
+                vec2(28.0, METER_HEIGHT),
+                egui::Label::new(egui::RichText::new(METER_NAMES[meter_idx]).strong()),
+            );
+            let width = (ui.available_width() - METER_HEIGHT - 8.0).max(40.0);
+            let (rect, response) =
+                ui.allocate_exact_size(vec2(width, METER_HEIGHT), Sense::hover());
+            meter_bar(ui.painter(), rect, rms_db, display);
+            response.on_hover_text(format!(
+                "RMS {rms_db:.1} dB, peak {:.1} dB",
+                display.hold_db
+            ));
+
+            // индикатор перегрузки горит, пока по нему не щелкнут
+            let (clip_rect, clip_response) =
+                ui.allocate_exact_size(vec2(METER_HEIGHT, METER_HEIGHT), Sense::click());
+            let clip_color = if display.clipped {
+                Color32::RED
+            } else {
+                ui.visuals().extreme_bg_color
+            };
+            ui.painter().rect_filled(clip_rect, 2.0, clip_color);
+            if clip_response
+                .on_hover_text("Clip, click to reset")
+                .clicked()
+            {
+                display.clipped = false;
+            }
+        });
+    }
+
+    ui.data_mut(|data| data.insert_temp(display_id, displays));
+    // уровни меняются без ввода пользователя
+    ui.ctx().request_repaint();
+}
+
+// Полоса от MIN_DB до MAX_DB: RMS заливкой, пик линией, удержание риской
+fn meter_bar(painter: &egui::Painter, rect: Rect, rms_db: f32, display: &MeterDisplay) {
+    let x_of = |db: f32| {
+        let position = ((db - MIN_DB) / (MAX_DB - MIN_DB)).clamp(0.0, 1.0);
+        rect.left() + rect.width() * position
+    };
+    let color_of = |db: f32| {
+        if db >= 0.0 {
+            Color32::RED
+        } else if db >= -6.0 {
+            Color32::YELLOW
+        } else {
+            Color32::from_rgb(150, 255, 0)
+        }
+    };
+
+    painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+    let rms_rect = Rect::from_min_max(rect.min, egui::pos2(x_of(rms_db), rect.max.y));
+    painter.rect_filled(rms_rect, 2.0, color_of(rms_db).gamma_multiply(0.8));
+    let peak_x = x_of(display.peak_db);
+    painter.line_segment(
+        [
+            egui::pos2(peak_x, rect.center().y),
+            egui::pos2(peak_x, rect.max.y),
+        ],
+        Stroke::new(2.0, Color32::WHITE),
+    );
+    let hold_x = x_of(display.hold_db);
+    painter.line_segment(
+        [
+            egui::pos2(hold_x, rect.min.y),
+            egui::pos2(hold_x, rect.max.y),
+        ],
+        Stroke::new(2.0, color_of(display.hold_db)),
+    );
+    // отметка 0 дБ
+    let zero_x = x_of(0.0);
+    painter.line_segment(
+        [
+            egui::pos2(zero_x, rect.min.y),
+            egui::pos2(zero_x, rect.max.y),
+        ],
+        Stroke::new(1.0, Color32::from_gray(120)),
+    );
+    painter.text(
+        rect.right_center() - vec2(4.0, 0.0),
+        Align2::RIGHT_CENTER,
+        format!("{:.0}", display.hold_db),
+        FontId::proportional(9.0),
+        Color32::from_gray(200),
+    );
+}
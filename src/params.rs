@@ -0,0 +1,788 @@
+use nih_plug::prelude::*;
+use std::sync::Arc;
+
+pub use crate::engine::filter::{MAX_FILTER_CUTOFF, MIN_FILTER_CUTOFF};
+use crate::engine::tempo_sync::TransportInfo;
+use crate::engine::tuning::{MAX_FINE_TUNE, MAX_GLIDE, MAX_INTERVAL, TuningSettings};
+use crate::engine::voices::MAX_VOICES;
+use crate::engine::{EngineParams, LineSettings, MidSide, Routing, Stereo};
+pub use crate::engine::{
+    MAX_DELAY_TIME, MAX_LFO_DEPTH, MAX_LINES, MAX_STEREO_OFFSET, MIN_DELAY_TIME,
+};
+use crate::engine::{delay_line, lfo, saturation, stability, tempo_sync};
+
+pub const MAX_DRIVE: f32 = 36.0; // dB
+
+// Буквы линий используются и в ID параметров, и в названиях
+pub const LINE_IDS: [&str; MAX_LINES] = ["a", "b", "c", "d", "e", "f", "g", "h"];
+pub const LINE_NAMES: [&str; MAX_LINES] = ["A", "B", "C", "D", "E", "F", "G", "H"];
+
+// ID полифонической модуляции CLAP: эти параметры хост может менять отдельно для каждой ноты
+pub const FINE_TUNE_POLY_MOD_ID: u32 = 0;
+pub const EXCITATION_POLY_MOD_ID: u32 = 1;
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationMode {
+    #[id = "linear"]
+    #[name = "Linear"]
+    Linear,
+    #[id = "hermite"]
+    #[name = "Cubic Hermite"]
+    Hermite,
+    #[id = "lagrange"]
+    #[name = "Lagrange"]
+    Lagrange,
+    #[id = "allpass"]
+    #[name = "Allpass"]
+    Allpass,
+}
+
+impl From<InterpolationMode> for delay_line::Interpolation {
+    fn from(mode: InterpolationMode) -> Self {
+        match mode {
+            InterpolationMode::Linear => Self::Linear,
+            InterpolationMode::Hermite => Self::Hermite,
+            InterpolationMode::Lagrange => Self::Lagrange,
+            InterpolationMode::Allpass => Self::Allpass,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    #[id = "free"]
+    #[name = "Free (ms)"]
+    Free,
+    #[id = "straight"]
+    #[name = "Straight"]
+    Straight,
+    #[id = "dotted"]
+    #[name = "Dotted"]
+    Dotted,
+    #[id = "triplet"]
+    #[name = "Triplet"]
+    Triplet,
+}
+
+impl From<SyncMode> for tempo_sync::Mode {
+    fn from(mode: SyncMode) -> Self {
+        match mode {
+            SyncMode::Free => Self::Free,
+            SyncMode::Straight => Self::Straight,
+            SyncMode::Dotted => Self::Dotted,
+            SyncMode::Triplet => Self::Triplet,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteDivision {
+    #[id = "1/64"]
+    #[name = "1/64"]
+    SixtyFourth,
+    #[id = "1/32"]
+    #[name = "1/32"]
+    ThirtySecond,
+    #[id = "1/16"]
+    #[name = "1/16"]
+    Sixteenth,
+    #[id = "1/8"]
+    #[name = "1/8"]
+    Eighth,
+    #[id = "1/4"]
+    #[name = "1/4"]
+    Quarter,
+    #[id = "1/2"]
+    #[name = "1/2"]
+    Half,
+    #[id = "1_bar"]
+    #[name = "1 bar"]
+    Bar,
+    #[id = "2_bars"]
+    #[name = "2 bars"]
+    TwoBars,
+    #[id = "4_bars"]
+    #[name = "4 bars"]
+    FourBars,
+}
+
+impl From<NoteDivision> for tempo_sync::Division {
+    fn from(division: NoteDivision) -> Self {
+        match division {
+            NoteDivision::SixtyFourth => Self::SixtyFourth,
+            NoteDivision::ThirtySecond => Self::ThirtySecond,
+            NoteDivision::Sixteenth => Self::Sixteenth,
+            NoteDivision::Eighth => Self::Eighth,
+            NoteDivision::Quarter => Self::Quarter,
+            NoteDivision::Half => Self::Half,
+            NoteDivision::Bar => Self::Bar,
+            NoteDivision::TwoBars => Self::TwoBars,
+            NoteDivision::FourBars => Self::FourBars,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LfoShape {
+    #[id = "sine"]
+    #[name = "Sine"]
+    Sine,
+    #[id = "triangle"]
+    #[name = "Triangle"]
+    Triangle,
+    #[id = "saw"]
+    #[name = "Saw"]
+    Saw,
+    #[id = "sample_and_hold"]
+    #[name = "Sample & Hold"]
+    SampleAndHold,
+    #[id = "smooth_random"]
+    #[name = "Smooth Random"]
+    SmoothRandom,
+}
+
+impl From<LfoShape> for lfo::Shape {
+    fn from(shape: LfoShape) -> Self {
+        match shape {
+            LfoShape::Sine => Self::Sine,
+            LfoShape::Triangle => Self::Triangle,
+            LfoShape::Saw => Self::Saw,
+            LfoShape::SampleAndHold => Self::SampleAndHold,
+            LfoShape::SmoothRandom => Self::SmoothRandom,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveCurve {
+    #[id = "off"]
+    #[name = "Off"]
+    Off,
+    #[id = "tanh"]
+    #[name = "Tanh"]
+    Tanh,
+    #[id = "tube"]
+    #[name = "Asymmetric Tube"]
+    Tube,
+    #[id = "hard_clip"]
+    #[name = "Hard Clip"]
+    HardClip,
+    #[id = "cubic"]
+    #[name = "Cubic"]
+    Cubic,
+}
+
+impl From<DriveCurve> for saturation::Curve {
+    fn from(curve: DriveCurve) -> Self {
+        match curve {
+            DriveCurve::Off => Self::Off,
+            DriveCurve::Tanh => Self::Tanh,
+            DriveCurve::Tube => Self::Tube,
+            DriveCurve::HardClip => Self::HardClip,
+            DriveCurve::Cubic => Self::Cubic,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SafetyMode {
+    #[id = "off"]
+    #[name = "Off"]
+    Off,
+    #[id = "soft_clip"]
+    #[name = "Soft Clip"]
+    SoftClip,
+    #[id = "limiter"]
+    #[name = "Limiter"]
+    Limiter,
+}
+
+impl From<SafetyMode> for stability::Safety {
+    fn from(mode: SafetyMode) -> Self {
+        match mode {
+            SafetyMode::Off => Self::Off,
+            SafetyMode::SoftClip => Self::SoftClip,
+            SafetyMode::Limiter => Self::Limiter,
+        }
+    }
+}
+
+// Маршрутизация линии в стерео
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoutingMode {
+    #[id = "normal"]
+    #[name = "Normal"]
+    Normal,
+    #[id = "ping_pong"]
+    #[name = "Ping-Pong"]
+    PingPong,
+    #[id = "spread"]
+    #[name = "Mono Spread"]
+    Spread,
+}
+
+impl From<RoutingMode> for Routing {
+    fn from(mode: RoutingMode) -> Self {
+        match mode {
+            RoutingMode::Normal => Self::Normal,
+            RoutingMode::PingPong => Self::PingPong,
+            RoutingMode::Spread => Self::Spread,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoMode {
+    #[id = "left_right"]
+    #[name = "L/R"]
+    LeftRight,
+    #[id = "mid_side"]
+    #[name = "M/S"]
+    MidSide,
+}
+
+impl From<StereoMode> for Stereo {
+    fn from(mode: StereoMode) -> Self {
+        match mode {
+            StereoMode::LeftRight => Self::LeftRight,
+            StereoMode::MidSide => Self::MidSide,
+        }
+    }
+}
+
+// Каналы линии в режиме M/S
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidSideChannels {
+    #[id = "both"]
+    #[name = "Mid + Side"]
+    Both,
+    #[id = "mid"]
+    #[name = "Mid"]
+    Mid,
+    #[id = "side"]
+    #[name = "Side"]
+    Side,
+}
+
+impl From<MidSideChannels> for MidSide {
+    fn from(channels: MidSideChannels) -> Self {
+        match channels {
+            MidSideChannels::Both => Self::Both,
+            MidSideChannels::Mid => Self::Mid,
+            MidSideChannels::Side => Self::Side,
+        }
+    }
+}
+
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCount {
+    #[id = "2"]
+    #[name = "2 lines"]
+    Two,
+    #[id = "4"]
+    #[name = "4 lines"]
+    Four,
+    #[id = "8"]
+    #[name = "8 lines"]
+    Eight,
+}
+
+impl LineCount {
+    pub fn count(self) -> usize {
+        match self {
+            LineCount::Two => 2,
+            LineCount::Four => 4,
+            LineCount::Eight => MAX_LINES,
+        }
+    }
+}
+
+#[derive(Params)]
+pub struct DParams {
+    #[id = "in_send_out"]
+    pub in_send_out: FloatParam,
+    #[id = "line_count"]
+    pub line_count: EnumParam<LineCount>,
+    #[id = "safety"]
+    pub safety: EnumParam<SafetyMode>,
+    #[id = "stereo_mode"]
+    pub stereo_mode: EnumParam<StereoMode>,
+    #[id = "width"]
+    pub width: FloatParam,
+
+    #[nested]
+    pub matrix: SendMatrix,
+
+    #[nested(id_prefix = "line_a", group = "Line A")]
+    pub line_a: LineParams,
+    #[nested(id_prefix = "line_b", group = "Line B")]
+    pub line_b: LineParams,
+    #[nested(id_prefix = "line_c", group = "Line C")]
+    pub line_c: LineParams,
+    #[nested(id_prefix = "line_d", group = "Line D")]
+    pub line_d: LineParams,
+    #[nested(id_prefix = "line_e", group = "Line E")]
+    pub line_e: LineParams,
+    #[nested(id_prefix = "line_f", group = "Line F")]
+    pub line_f: LineParams,
+    #[nested(id_prefix = "line_g", group = "Line G")]
+    pub line_g: LineParams,
+    #[nested(id_prefix = "line_h", group = "Line H")]
+    pub line_h: LineParams,
+
+    #[nested(group = "Tuning")]
+    pub tuning: TuningParams,
+}
+
+impl DParams {
+    pub fn line(&self, line_idx: usize) -> &LineParams {
+        match line_idx {
+            0 => &self.line_a,
+            1 => &self.line_b,
+            2 => &self.line_c,
+            3 => &self.line_d,
+            4 => &self.line_e,
+            5 => &self.line_f,
+            6 => &self.line_g,
+            _ => &self.line_h,
+        }
+    }
+
+    // Снимок текущих значений для движка, сглаживание движок делает сам
+    pub fn engine_params(&self, transport: TransportInfo) -> EngineParams {
+        EngineParams {
+            dry: self.in_send_out.value(),
+            line_count: self.line_count.value().count(),
+            safety: self.safety.value().into(),
+            stereo_mode: self.stereo_mode.value().into(),
+            width: self.width.value(),
+            input_sends: std::array::from_fn(|to| self.matrix.input[to].value()),
+            sends: std::array::from_fn(|from| {
+                std::array::from_fn(|to| self.matrix.send(from, to).value())
+            }),
+            lines: std::array::from_fn(|line_idx| self.line(line_idx).settings()),
+            transport,
+            tuning: self.tuning.settings(),
+        }
+    }
+}
+
+impl Default for DParams {
+    fn default() -> Self {
+        Self {
+            in_send_out: send_param("Dry Level", 100.0),
+            line_count: EnumParam::new("Line Count", LineCount::Two).non_automatable(),
+            safety: EnumParam::new("Feedback Safety", SafetyMode::Off),
+            stereo_mode: EnumParam::new("Stereo Mode", StereoMode::LeftRight),
+            width: FloatParam::new(
+                "M/S Width",
+                100.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 200.0,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.0}%", s))),
+
+            matrix: SendMatrix::default(),
+
+            line_a: LineParams::new(LINE_NAMES[0]),
+            line_b: LineParams::new(LINE_NAMES[1]),
+            line_c: LineParams::new(LINE_NAMES[2]),
+            line_d: LineParams::new(LINE_NAMES[3]),
+            line_e: LineParams::new(LINE_NAMES[4]),
+            line_f: LineParams::new(LINE_NAMES[5]),
+            line_g: LineParams::new(LINE_NAMES[6]),
+            line_h: LineParams::new(LINE_NAMES[7]),
+
+            tuning: TuningParams::default(),
+        }
+    }
+}
+
+// Игра по MIDI: линии A и B настраиваются на высоту ноты
+#[derive(Params)]
+pub struct TuningParams {
+    #[id = "note_tuning"]
+    pub enabled: BoolParam,
+    #[id = "fine_tune"]
+    pub fine_tune: FloatParam,
+    #[id = "interval_b"]
+    pub interval_b: IntParam,
+    #[id = "glide"]
+    pub glide: FloatParam,
+    #[id = "excitation"]
+    pub excitation: FloatParam,
+    #[id = "voices"]
+    pub voices: IntParam,
+}
+
+impl TuningParams {
+    pub fn settings(&self) -> TuningSettings {
+        TuningSettings {
+            enabled: self.enabled.value(),
+            fine_tune: self.fine_tune.value(),
+            interval_b: self.interval_b.value() as f32,
+            glide: self.glide.value(),
+            excitation: self.excitation.value(),
+            voices: self.voices.value() as usize,
+        }
+    }
+}
+
+impl Default for TuningParams {
+    fn default() -> Self {
+        Self {
+            enabled: BoolParam::new("Note Tuning", false),
+            fine_tune: FloatParam::new(
+                "Fine Tune",
+                0.0,
+                FloatRange::Linear {
+                    min: -MAX_FINE_TUNE,
+                    max: MAX_FINE_TUNE,
+                },
+            )
+            .with_poly_modulation_id(FINE_TUNE_POLY_MOD_ID)
+            .with_value_to_string(Arc::new(|s| format!("{:.1} ct", s))),
+            interval_b: IntParam::new(
+                "Interval B",
+                0,
+                IntRange::Linear {
+                    min: -MAX_INTERVAL,
+                    max: MAX_INTERVAL,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{s} st"))),
+            glide: FloatParam::new(
+                "Glide",
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: MAX_GLIDE,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.0} ms", s))),
+            excitation: FloatParam::new(
+                "Excite",
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 100.0,
+                },
+            )
+            .with_poly_modulation_id(EXCITATION_POLY_MOD_ID)
+            .with_value_to_string(Arc::new(|s| format!("{:.0}%", s))),
+            // 1 - одноголосый режим на самих линиях A и B
+            voices: IntParam::new(
+                "Voices",
+                1,
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_VOICES as i32,
+                },
+            ),
+        }
+    }
+}
+
+// Все посылы матрицы: со входа в линии и из линии в линию.
+// ID генерируются по схеме исходной 2-линейной версии, чтобы старые сессии загружались:
+// in_send_a, a_to_b_send, line_a_feedback (диагональ) и т.д.
+pub struct SendMatrix {
+    pub input: Vec<FloatParam>,
+    // sends[from][to]
+    pub sends: Vec<Vec<FloatParam>>,
+}
+
+impl SendMatrix {
+    pub fn send(&self, from: usize, to: usize) -> &FloatParam {
+        &self.sends[from][to]
+    }
+}
+
+impl Default for SendMatrix {
+    fn default() -> Self {
+        Self {
+            input: LINE_NAMES
+                .iter()
+                .map(|to| send_param(format!("Input to {to}"), 100.0))
+                .collect(),
+            sends: LINE_NAMES
+                .iter()
+                .map(|from| {
+                    LINE_NAMES
+                        .iter()
+                        .map(|to| {
+                            if from == to {
+                                send_param(format!("Feedback {from}"), 0.0)
+                            } else {
+                                send_param(format!("{from} to {to}"), 0.0)
+                            }
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+unsafe impl Params for SendMatrix {
+    fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+        let input = self.input.iter().zip(LINE_IDS).map(|(param, to)| {
+            (
+                format!("in_send_{to}"),
+                param.as_ptr(),
+                String::from("Input"),
+            )
+        });
+        let sends = self
+            .sends
+            .iter()
+            .zip(LINE_IDS.iter().zip(LINE_NAMES))
+            .flat_map(|(row, (from, from_name))| {
+                row.iter().zip(LINE_IDS).map(move |(param, to)| {
+                    let id = if *from == to {
+                        format!("line_{from}_feedback")
+                    } else {
+                        format!("{from}_to_{to}_send")
+                    };
+                    (id, param.as_ptr(), format!("Line {from_name}"))
+                })
+            });
+
+        input.chain(sends).collect()
+    }
+}
+
+#[derive(Params)]
+pub struct LineParams {
+    #[id = "level"]
+    pub level: FloatParam,
+    #[id = "delay"]
+    pub delay: FloatParam,
+    #[id = "interpolation"]
+    pub interpolation: EnumParam<InterpolationMode>,
+    #[id = "sync"]
+    pub sync: EnumParam<SyncMode>,
+    #[id = "division"]
+    pub division: EnumParam<NoteDivision>,
+    #[id = "lfo_shape"]
+    pub lfo_shape: EnumParam<LfoShape>,
+    #[id = "lfo_rate"]
+    pub lfo_rate: FloatParam,
+    #[id = "lfo_depth"]
+    pub lfo_depth: FloatParam,
+    #[id = "lfo_stereo"]
+    pub lfo_stereo: FloatParam,
+    #[id = "lowpass"]
+    pub lowpass: FloatParam,
+    #[id = "highpass"]
+    pub highpass: FloatParam,
+    #[id = "resonance"]
+    pub resonance: FloatParam,
+    #[id = "drive_curve"]
+    pub drive_curve: EnumParam<DriveCurve>,
+    #[id = "drive"]
+    pub drive: FloatParam,
+    #[id = "drive_bias"]
+    pub drive_bias: FloatParam,
+    #[id = "drive_trim"]
+    pub drive_trim: FloatParam,
+    #[id = "pan"]
+    pub pan: FloatParam,
+    #[id = "stereo_offset"]
+    pub stereo_offset: FloatParam,
+    #[id = "routing"]
+    pub routing: EnumParam<RoutingMode>,
+    #[id = "swap_sends"]
+    pub swap_sends: BoolParam,
+    #[id = "mid_side"]
+    pub mid_side: EnumParam<MidSideChannels>,
+}
+
+impl LineParams {
+    pub fn settings(&self) -> LineSettings {
+        LineSettings {
+            level: self.level.value(),
+            delay: self.delay.value(),
+            interpolation: self.interpolation.value().into(),
+            sync: self.sync.value().into(),
+            division: self.division.value().into(),
+            lfo_shape: self.lfo_shape.value().into(),
+            lfo_rate: self.lfo_rate.value(),
+            lfo_depth: self.lfo_depth.value(),
+            lfo_stereo: self.lfo_stereo.value(),
+            lowpass: self.lowpass.value(),
+            highpass: self.highpass.value(),
+            resonance: self.resonance.value(),
+            drive_curve: self.drive_curve.value().into(),
+            drive: self.drive.value(),
+            drive_bias: self.drive_bias.value(),
+            drive_trim: self.drive_trim.value(),
+            pan: self.pan.value(),
+            stereo_offset: self.stereo_offset.value(),
+            routing: self.routing.value().into(),
+            swap_sends: self.swap_sends.value(),
+            mid_side: self.mid_side.value().into(),
+        }
+    }
+
+    pub fn new(name: &str) -> Self {
+        Self {
+            level: send_param(format!("{name} to out"), 0.0),
+            delay: FloatParam::new(
+                format!("Delay {name}"),
+                500.0,
+                FloatRange::Linear {
+                    min: MIN_DELAY_TIME,
+                    max: MAX_DELAY_TIME,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.3} ms", s))),
+
+            interpolation: EnumParam::new(
+                format!("Interpolation {name}"),
+                InterpolationMode::Linear,
+            ),
+            sync: EnumParam::new(format!("Sync {name}"), SyncMode::Free),
+            division: EnumParam::new(format!("Division {name}"), NoteDivision::Quarter),
+
+            lfo_shape: EnumParam::new(format!("LFO Shape {name}"), LfoShape::Sine),
+            lfo_rate: FloatParam::new(
+                format!("LFO Rate {name}"),
+                0.5,
+                FloatRange::Skewed {
+                    min: 0.01,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.2} Hz", s))),
+            lfo_depth: FloatParam::new(
+                format!("LFO Depth {name}"),
+                0.0,
+                FloatRange::Skewed {
+                    min: 0.0,
+                    max: MAX_LFO_DEPTH,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.3} ms", s))),
+            lfo_stereo: FloatParam::new(
+                format!("LFO Stereo {name}"),
+                90.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: 360.0,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.0}°", s))),
+
+            lowpass: FloatParam::new(
+                format!("Low-pass {name}"),
+                MAX_FILTER_CUTOFF,
+                FloatRange::Skewed {
+                    min: MIN_FILTER_CUTOFF,
+                    max: MAX_FILTER_CUTOFF,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.0} Hz", s))),
+            highpass: FloatParam::new(
+                format!("High-pass {name}"),
+                MIN_FILTER_CUTOFF,
+                FloatRange::Skewed {
+                    min: MIN_FILTER_CUTOFF,
+                    max: MAX_FILTER_CUTOFF,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.0} Hz", s))),
+            resonance: FloatParam::new(
+                format!("Resonance {name}"),
+                std::f32::consts::FRAC_1_SQRT_2,
+                FloatRange::Skewed {
+                    min: 0.5,
+                    max: 10.0,
+                    factor: FloatRange::skew_factor(-1.5),
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("Q {:.2}", s))),
+
+            drive_curve: EnumParam::new(format!("Drive Curve {name}"), DriveCurve::Off),
+            drive: FloatParam::new(
+                format!("Drive {name}"),
+                0.0,
+                FloatRange::Linear {
+                    min: 0.0,
+                    max: MAX_DRIVE,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.1} dB", s))),
+            drive_bias: FloatParam::new(
+                format!("Drive Bias {name}"),
+                0.0,
+                FloatRange::Linear {
+                    min: -0.5,
+                    max: 0.5,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.2}", s))),
+            drive_trim: FloatParam::new(
+                format!("Drive Trim {name}"),
+                0.0,
+                FloatRange::Linear {
+                    min: -MAX_DRIVE,
+                    max: 12.0,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.1} dB", s))),
+
+            pan: FloatParam::new(
+                format!("Pan {name}"),
+                0.0,
+                FloatRange::Linear {
+                    min: -100.0,
+                    max: 100.0,
+                },
+            )
+            .with_value_to_string(Arc::new(pan_to_string)),
+            stereo_offset: FloatParam::new(
+                format!("Stereo Offset {name}"),
+                0.0,
+                FloatRange::Linear {
+                    min: -MAX_STEREO_OFFSET,
+                    max: MAX_STEREO_OFFSET,
+                },
+            )
+            .with_value_to_string(Arc::new(|s| format!("{:.3} ms", s))),
+            routing: EnumParam::new(format!("Routing {name}"), RoutingMode::Normal),
+            swap_sends: BoolParam::new(format!("Swap Sends {name}"), false),
+            mid_side: EnumParam::new(format!("M/S Channels {name}"), MidSideChannels::Both),
+        }
+    }
+}
+
+// Посыл в процентах от -100 до 100
+fn send_param(name: impl Into<String>, default: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default,
+        FloatRange::Linear {
+            min: -100.0,
+            max: 100.0,
+        },
+    )
+    .with_value_to_string(Arc::new(|s| format!("{:.2}%", s)))
+}
+
+fn pan_to_string(pan: f32) -> String {
+    if pan.abs() < 0.5 {
+        String::from("C")
+    } else if pan < 0.0 {
+        format!("L {:.0}", -pan)
+    } else {
+        format!("R {:.0}", pan)
+    }
+}
@@ -0,0 +1,334 @@
+// Пресеты в JSON: значения всех параметров по их ID плюс метаданные.
+// ID те же, что видит хост, поэтому значения можно переносить из сессии как есть.
+// Числа хранятся в единицах ручек (%, мс, Гц, дБ), переключатели - по ID вариантов,
+// флажки - как true/false
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::engine::tuning::MAX_INTERVAL;
+use crate::engine::voices::MAX_VOICES;
+use crate::engine::{EngineParams, LineSettings};
+use crate::params::{
+    DParams, DriveCurve, InterpolationMode, LINE_IDS, LfoShape, LineCount, MidSideChannels,
+    NoteDivision, RoutingMode, SafetyMode, StereoMode, SyncMode,
+};
+
+// версия формата файла, а не плагина
+pub const PRESET_VERSION: u32 = 1;
+pub const PRESET_EXTENSION: &str = "json";
+
+pub const FACTORY_PRESETS: [&str; 5] = [
+    include_str!("../presets/slapback.json"),
+    include_str!("../presets/ping_pong.json"),
+    include_str!("../presets/karplus_pluck.json"),
+    include_str!("../presets/comb_flanger.json"),
+    include_str!("../presets/cross_feedback_wash.json"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "current_version")]
+    pub version: u32,
+    // параметры, которых нет в пресете, получают значения по умолчанию
+    pub params: BTreeMap<String, Value>,
+}
+
+fn current_version() -> u32 {
+    PRESET_VERSION
+}
+
+impl Preset {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let preset: Self = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        if preset.version > PRESET_VERSION {
+            return Err(format!(
+                "preset version {} is newer than supported {PRESET_VERSION}",
+                preset.version
+            ));
+        }
+        Ok(preset)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("preset values are always serializable")
+    }
+
+    // Снимок текущих значений всех параметров плагина
+    pub fn capture(name: &str, params: &DParams) -> Self {
+        let values = params
+            .param_map()
+            .into_iter()
+            .map(|(id, param_ptr, _)| {
+                // SAFETY: указатели из param_map живут, пока живы сами параметры
+                let plain = unsafe { param_ptr.unmodulated_plain_value() };
+                let value = match (choices(&id), param_ptr) {
+                    // у переключателей plain - индекс варианта
+                    (Some(choices), _) => Value::String(choices.ids[plain as usize].to_string()),
+                    (None, ParamPtr::BoolParam(_)) => Value::Bool(plain >= 0.5),
+                    (None, _) => json_number(plain),
+                };
+                (id, value)
+            })
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            author: String::new(),
+            tags: Vec::new(),
+            version: PRESET_VERSION,
+            params: values,
+        }
+    }
+
+    // Применение к параметрам плагина из редактора: сначала все проверяется,
+    // чтобы ошибка в файле не оставила полупримененный пресет
+    pub fn load(&self, params: &DParams, setter: &ParamSetter) -> Result<(), String> {
+        let param_map = params.param_map();
+        if let Some(id) = self
+            .params
+            .keys()
+            .find(|id| !param_map.iter().any(|(param_id, _, _)| param_id == *id))
+        {
+            return Err(format!("unknown parameter {id}"));
+        }
+
+        let mut normalized_values = Vec::with_capacity(param_map.len());
+        for (id, param_ptr, _) in &param_map {
+            // SAFETY: указатели из param_map живут, пока живы сами параметры
+            let normalized = match self.params.get(id) {
+                None => unsafe { param_ptr.default_normalized_value() },
+                Some(value) => {
+                    let value = value_text(id, value)?;
+                    let plain = match (choices(id), param_ptr) {
+                        (Some(choices), _) => choices.parse(&value)? as f32,
+                        (None, ParamPtr::BoolParam(_)) => parse_bool(&value)? as u8 as f32,
+                        (None, _) => parse_float(&value)?,
+                    };
+                    unsafe { param_ptr.preview_normalized(plain) }
+                }
+            };
+            normalized_values.push((*param_ptr, normalized));
+        }
+
+        for (param_ptr, normalized) in normalized_values {
+            // SAFETY: как и выше, параметры принадлежат плагину, редактор живет не дольше него
+            unsafe {
+                setter.raw_context.raw_begin_set_parameter(param_ptr);
+                setter
+                    .raw_context
+                    .raw_set_parameter_normalized(param_ptr, normalized);
+                setter.raw_context.raw_end_set_parameter(param_ptr);
+            }
+        }
+        Ok(())
+    }
+
+    // Применение к параметрам движка, без хоста - для рендера из командной строки
+    pub fn apply(&self, params: &mut EngineParams) -> Result<(), String> {
+        for (id, value) in &self.params {
+            apply_param(params, id, &value_text(id, value)?)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn factory_bank() -> Vec<Preset> {
+    FACTORY_PRESETS
+        .iter()
+        .map(|json| Preset::from_json(json).expect("factory presets are valid"))
+        .collect()
+}
+
+// Все ID в том же порядке, в каком их отдает плагин
+pub fn param_ids() -> Vec<String> {
+    DParams::default()
+        .param_map()
+        .into_iter()
+        .map(|(id, _, _)| id)
+        .collect()
+}
+
+pub fn apply_param(params: &mut EngineParams, id: &str, value: &str) -> Result<(), String> {
+    match id {
+        "in_send_out" => params.dry = parse_float(value)?,
+        "line_count" => params.line_count = parse_enum::<LineCount>(value)?.count(),
+        "safety" => params.safety = parse_enum::<SafetyMode>(value)?.into(),
+        "stereo_mode" => params.stereo_mode = parse_enum::<StereoMode>(value)?.into(),
+        "width" => params.width = parse_float(value)?,
+        "note_tuning" => params.tuning.enabled = parse_bool(value)?,
+        "fine_tune" => params.tuning.fine_tune = parse_float(value)?,
+        // целые параметры плагина: значение из файла округляется и зажимается так же
+        "interval_b" => {
+            params.tuning.interval_b = parse_float(value)?
+                .round()
+                .clamp(-MAX_INTERVAL as f32, MAX_INTERVAL as f32)
+        }
+        "glide" => params.tuning.glide = parse_float(value)?,
+        "excitation" => params.tuning.excitation = parse_float(value)?,
+        "voices" => {
+            params.tuning.voices =
+                parse_float(value)?.round().clamp(1.0, MAX_VOICES as f32) as usize
+        }
+        _ => {
+            if let Some(to) = id.strip_prefix("in_send_") {
+                params.input_sends[line_idx(to, id)?] = parse_float(value)?;
+            } else if let Some((from, to)) = id
+                .strip_suffix("_send")
+                .and_then(|pair| pair.split_once("_to_"))
+            {
+                params.sends[line_idx(from, id)?][line_idx(to, id)?] = parse_float(value)?;
+            } else if let Some(line) = id
+                .strip_prefix("line_")
+                .and_then(|rest| rest.strip_suffix("_feedback"))
+            {
+                let line_idx = line_idx(line, id)?;
+                params.sends[line_idx][line_idx] = parse_float(value)?;
+            } else if let Some((line, line_id)) = split_line_id(id) {
+                let line_idx = line_idx(line, id)?;
+                if !apply_line_param(&mut params.lines[line_idx], line_id, value)? {
+                    return Err(format!("unknown parameter {id}"));
+                }
+            } else {
+                return Err(format!("unknown parameter {id}"));
+            }
+        }
+    }
+    Ok(())
+}
+
+// false, если такого параметра у линии нет
+fn apply_line_param(line: &mut LineSettings, id: &str, value: &str) -> Result<bool, String> {
+    match id {
+        "level" => line.level = parse_float(value)?,
+        "delay" => line.delay = parse_float(value)?,
+        "interpolation" => line.interpolation = parse_enum::<InterpolationMode>(value)?.into(),
+        "sync" => line.sync = parse_enum::<SyncMode>(value)?.into(),
+        "division" => line.division = parse_enum::<NoteDivision>(value)?.into(),
+        "lfo_shape" => line.lfo_shape = parse_enum::<LfoShape>(value)?.into(),
+        "lfo_rate" => line.lfo_rate = parse_float(value)?,
+        "lfo_depth" => line.lfo_depth = parse_float(value)?,
+        "lfo_stereo" => line.lfo_stereo = parse_float(value)?,
+        "lowpass" => line.lowpass = parse_float(value)?,
+        "highpass" => line.highpass = parse_float(value)?,
+        "resonance" => line.resonance = parse_float(value)?,
+        "drive_curve" => line.drive_curve = parse_enum::<DriveCurve>(value)?.into(),
+        "drive" => line.drive = parse_float(value)?,
+        "drive_bias" => line.drive_bias = parse_float(value)?,
+        "drive_trim" => line.drive_trim = parse_float(value)?,
+        "pan" => line.pan = parse_float(value)?,
+        "stereo_offset" => line.stereo_offset = parse_float(value)?,
+        "routing" => line.routing = parse_enum::<RoutingMode>(value)?.into(),
+        "swap_sends" => line.swap_sends = parse_bool(value)?,
+        "mid_side" => line.mid_side = parse_enum::<MidSideChannels>(value)?.into(),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+// line_a_lfo_shape -> ("a", "lfo_shape")
+fn split_line_id(id: &str) -> Option<(&str, &str)> {
+    id.strip_prefix("line_")
+        .and_then(|rest| rest.split_once('_'))
+}
+
+fn line_idx(line: &str, id: &str) -> Result<usize, String> {
+    LINE_IDS
+        .iter()
+        .position(|line_id| *line_id == line)
+        .ok_or_else(|| format!("unknown parameter {id}"))
+}
+
+// Варианты переключателя: ID (как в сохраненном состоянии) и имена из интерфейса
+struct Choices {
+    ids: &'static [&'static str],
+    names: &'static [&'static str],
+}
+
+impl Choices {
+    fn of<T: Enum>() -> Self {
+        Self {
+            ids: T::ids().unwrap_or(T::variants()),
+            names: T::variants(),
+        }
+    }
+
+    fn parse(&self, value: &str) -> Result<usize, String> {
+        let value = value.trim();
+        let find = |choices: &[&str]| {
+            choices
+                .iter()
+                .position(|choice| choice.eq_ignore_ascii_case(value))
+        };
+        find(self.ids).or_else(|| find(self.names)).ok_or_else(|| {
+            format!(
+                "invalid value {value}, expected one of: {}",
+                self.ids.join(", ")
+            )
+        })
+    }
+}
+
+fn choices(id: &str) -> Option<Choices> {
+    let line_id = match id {
+        "line_count" => return Some(Choices::of::<LineCount>()),
+        "safety" => return Some(Choices::of::<SafetyMode>()),
+        "stereo_mode" => return Some(Choices::of::<StereoMode>()),
+        _ => split_line_id(id)?.1,
+    };
+    match line_id {
+        "interpolation" => Some(Choices::of::<InterpolationMode>()),
+        "sync" => Some(Choices::of::<SyncMode>()),
+        "division" => Some(Choices::of::<NoteDivision>()),
+        "lfo_shape" => Some(Choices::of::<LfoShape>()),
+        "drive_curve" => Some(Choices::of::<DriveCurve>()),
+        "routing" => Some(Choices::of::<RoutingMode>()),
+        "mid_side" => Some(Choices::of::<MidSideChannels>()),
+        _ => None,
+    }
+}
+
+fn parse_enum<T: Enum>(value: &str) -> Result<T, String> {
+    Choices::of::<T>().parse(value).map(T::from_index)
+}
+
+fn parse_float(value: &str) -> Result<f32, String> {
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("invalid number {value}"))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => Err(format!("invalid value {value}, expected true or false")),
+    }
+}
+
+fn value_text(id: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(format!("invalid value for {id}: {value}")),
+    }
+}
+
+// Через строку, чтобы 0.1f32 не превращалось в 0.10000000149011612
+fn json_number(value: f32) -> Value {
+    value
+        .to_string()
+        .parse::<serde_json::Number>()
+        .map_or(Value::Null, Value::Number)
+}
@@ -0,0 +1,253 @@
+// Импульсная и амплитудно-частотная характеристики матрицы в редакторе. Отклик считает
+// отдельный поток движком из engine::analysis, редактор только рисует последний результат
+use crate::engine::analysis;
+use crate::engine::tempo_sync::TransportInfo;
+use crate::engine::{EngineParams, MatrixDelayEngine};
+use atomic_float::{AtomicF32, AtomicF64};
+use nih_plug_egui::egui::{self, Align2, Color32, FontId, Rect, Sense, Stroke, pos2, vec2};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+
+// длина отклика в сэмплах, степень двойки для БПФ
+const RESPONSE_LEN: usize = 8192;
+const BLOCK_SIZE: usize = 512;
+const PLOT_HEIGHT: f32 = 120.0;
+const MIN_FREQUENCY: f32 = 20.0;
+const MAX_FREQUENCY: f32 = 20000.0;
+const MIN_DB: f32 = -36.0;
+const MAX_DB: f32 = 24.0;
+const PLOT_COLOR: Color32 = Color32::from_rgb(0, 255, 255);
+
+// Частота дискретизации и транспорт хоста: отклик считается при тех же условиях, что и звук
+#[derive(Debug, Default)]
+pub struct HostInfo {
+    samplerate: AtomicF32,
+    // 0 - хост не сообщил темп
+    tempo: AtomicF64,
+    // 0 - хост не сообщил размер
+    numerator: AtomicI32,
+    denominator: AtomicI32,
+}
+
+impl HostInfo {
+    pub fn set_samplerate(&self, samplerate: f32) {
+        self.samplerate.store(samplerate, Ordering::Relaxed);
+    }
+
+    pub fn set_transport(&self, transport: &TransportInfo) {
+        self.tempo
+            .store(transport.tempo.unwrap_or(0.0), Ordering::Relaxed);
+        let (numerator, denominator) = transport.time_signature.unwrap_or((0, 0));
+        self.numerator.store(numerator, Ordering::Relaxed);
+        self.denominator.store(denominator, Ordering::Relaxed);
+    }
+
+    pub fn samplerate(&self) -> f32 {
+        self.samplerate.load(Ordering::Relaxed)
+    }
+
+    pub fn transport(&self) -> TransportInfo {
+        let tempo = self.tempo.load(Ordering::Relaxed);
+        let numerator = self.numerator.load(Ordering::Relaxed);
+        let denominator = self.denominator.load(Ordering::Relaxed);
+        TransportInfo {
+            tempo: (tempo > 0.0).then_some(tempo),
+            time_signature: (numerator > 0 && denominator > 0).then_some((numerator, denominator)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    samplerate: f32,
+    impulse: Vec<f32>,
+    // дБ по частотам k * samplerate / RESPONSE_LEN
+    magnitude: Vec<f32>,
+}
+
+type Request = (EngineParams, f32);
+
+// Поток расчета запускается при первом запросе и завершается вместе с редактором
+#[derive(Debug, Default)]
+pub struct ResponseWorker {
+    requests: OnceLock<Sender<Request>>,
+    last_request: Mutex<Option<Request>>,
+    response: Arc<Mutex<Option<Arc<Response>>>>,
+}
+
+impl ResponseWorker {
+    // Новый расчет, только если параметры изменились
+    fn request(&self, params: EngineParams, samplerate: f32) {
+        let request = (params, samplerate);
+        let mut last_request = self.last_request.lock().unwrap();
+        if last_request.as_ref() == Some(&request) {
+            return;
+        }
+        let requests = self.requests.get_or_init(|| self.spawn());
+        if requests.send(request.clone()).is_ok() {
+            *last_request = Some(request);
+        }
+    }
+
+    fn spawn(&self) -> Sender<Request> {
+        let (sender, receiver) = mpsc::channel::<Request>();
+        let response = self.response.clone();
+        let spawned = thread::Builder::new()
+            .name("microdelay-response".into())
+            .spawn(move || {
+                let mut engine = MatrixDelayEngine::default();
+                while let Ok(mut request) = receiver.recv() {
+                    // пока шел расчет, параметры могли поменяться несколько раз: нужен последний
+                    while let Ok(newer) = receiver.try_recv() {
+                        request = newer;
+                    }
+                    let (params, samplerate) = request;
+                    if engine.samplerate() != samplerate {
+                        engine.init(2, BLOCK_SIZE, samplerate);
+                    }
+                    let impulse = analysis::impulse_response(&mut engine, &params, RESPONSE_LEN);
+                    let magnitude = analysis::magnitude_response(&impulse);
+                    *response.lock().unwrap() = Some(Arc::new(Response {
+                        samplerate,
+                        impulse,
+                        magnitude,
+                    }));
+                }
+            });
+        if let Err(err) = spawned {
+            nih_plug::nih_error!("Failed to start response thread: {err}");
+        }
+        sender
+    }
+
+    fn response(&self) -> Option<Arc<Response>> {
+        self.response.lock().unwrap().clone()
+    }
+}
+
+pub fn response_panel(
+    ui: &mut egui::Ui,
+    worker: &ResponseWorker,
+    params: EngineParams,
+    samplerate: f32,
+) {
+    // до initialize частота неизвестна
+    if samplerate > 0.0 {
+        worker.request(params, samplerate);
+    }
+    let Some(response) = worker.response() else {
+        ui.label("Calculating...");
+        return;
+    };
+
+    ui.columns(2, |columns| {
+        columns[0].label(format!(
+            "Impulse response, {:.0} ms",
+            RESPONSE_LEN as f32 / response.samplerate * 1e3
+        ));
+        impulse_plot(&mut columns[0], &response.impulse);
+        columns[1].label("Magnitude response");
+        magnitude_plot(&mut columns[1], &response.magnitude, response.samplerate);
+    });
+}
+
+fn plot_area(ui: &mut egui::Ui) -> (Rect, egui::Painter) {
+    let (rect, _) = ui.allocate_exact_size(vec2(ui.available_width(), PLOT_HEIGHT), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+    (rect, painter)
+}
+
+// Отклик по столбцам: в каждом столбце размах отсчетов, масштаб по наибольшему
+fn impulse_plot(ui: &mut egui::Ui, impulse: &[f32]) {
+    let (rect, painter) = plot_area(ui);
+    let scale = impulse
+        .iter()
+        .fold(0.0f32, |peak, value| peak.max(value.abs()))
+        .max(1e-6);
+    let y_of = |value: f32| rect.center().y - value / scale * rect.height() * 0.45;
+    painter.hline(
+        rect.x_range(),
+        rect.center().y,
+        Stroke::new(1.0, Color32::from_gray(80)),
+    );
+
+    let columns = rect.width().max(1.0) as usize;
+    for column in 0..columns {
+        let start = column * impulse.len() / columns;
+        let end = ((column + 1) * impulse.len() / columns).max(start + 1);
+        let (min, max) = impulse[start..end.min(impulse.len())]
+            .iter()
+            .fold((0.0f32, 0.0f32), |(min, max), &value| {
+                (min.min(value), max.max(value))
+            });
+        let x = rect.left() + column as f32 + 0.5;
+        painter.line_segment(
+            [pos2(x, y_of(max)), pos2(x, y_of(min))],
+            Stroke::new(1.0, PLOT_COLOR),
+        );
+    }
+}
+
+// АЧХ по логарифмической оси частот, в каждом столбце - наибольшая амплитуда его бинов
+fn magnitude_plot(ui: &mut egui::Ui, magnitude: &[f32], samplerate: f32) {
+    let (rect, painter) = plot_area(ui);
+    let max_frequency = MAX_FREQUENCY.min(samplerate / 2.0);
+    let frequency_of = |x: f32| {
+        MIN_FREQUENCY * (max_frequency / MIN_FREQUENCY).powf((x - rect.left()) / rect.width())
+    };
+    let x_of = |frequency: f32| {
+        rect.left()
+            + rect.width() * (frequency / MIN_FREQUENCY).ln() / (max_frequency / MIN_FREQUENCY).ln()
+    };
+    let y_of = |db: f32| {
+        rect.bottom() - rect.height() * ((db - MIN_DB) / (MAX_DB - MIN_DB)).clamp(0.0, 1.0)
+    };
+    let bin_of = |frequency: f32| {
+        ((frequency / samplerate * RESPONSE_LEN as f32) as usize).min(magnitude.len() - 1)
+    };
+
+    // сетка: 0 дБ и декады частот
+    let grid = Stroke::new(1.0, Color32::from_gray(60));
+    painter.hline(rect.x_range(), y_of(0.0), grid);
+    for frequency in [100.0, 1000.0, 10000.0] {
+        if frequency < max_frequency {
+            let x = x_of(frequency);
+            painter.vline(x, rect.y_range(), grid);
+            painter.text(
+                pos2(x + 2.0, rect.bottom() - 2.0),
+                Align2::LEFT_BOTTOM,
+                if frequency < 1000.0 {
+                    format!("{frequency:.0}")
+                } else {
+                    format!("{:.0}k", frequency / 1000.0)
+                },
+                FontId::proportional(9.0),
+                Color32::from_gray(140),
+            );
+        }
+    }
+    painter.text(
+        pos2(rect.left() + 2.0, y_of(0.0) - 1.0),
+        Align2::LEFT_BOTTOM,
+        "0 dB",
+        FontId::proportional(9.0),
+        Color32::from_gray(140),
+    );
+
+    let columns = rect.width().max(1.0) as usize;
+    let points = (0..columns)
+        .map(|column| {
+            let x = rect.left() + column as f32;
+            let start = bin_of(frequency_of(x));
+            let end = bin_of(frequency_of(x + 1.0)).max(start);
+            let db = magnitude[start..=end]
+                .iter()
+                .fold(f32::NEG_INFINITY, |max, &db| max.max(db));
+            pos2(x, y_of(db))
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, Stroke::new(1.5, PLOT_COLOR)));
+}
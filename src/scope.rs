@@ -0,0 +1,223 @@
+// Осциллограф буферов линий: что сейчас ходит по петле. Аудиопоток несколько раз в секунду
+// прореживает буфер каждой линии за окном чуть длиннее задержки и кладет в атомики,
+// редактор рисует огибающую, головку записи и точку чтения.
+// Снимок может смешать два обновления, для картинки это незаметно
+use crate::engine::delay_line::DelayLine;
+use crate::engine::{MAX_LINES, MatrixDelayEngine};
+use atomic_float::AtomicF32;
+use nih_plug_egui::egui::{self, Align2, Color32, FontId, Sense, Stroke, pos2, vec2};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// столбцов огибающей на линию
+const SCOPE_BINS: usize = 256;
+// обновлений в секунду
+const SNAPSHOT_RATE: f32 = 15.0;
+// в длинных окнах в столбце смотрится не больше стольких сэмплов
+const MAX_SAMPLES_PER_BIN: usize = 32;
+// окно - задержка с запасом, чтобы точка чтения была внутри
+const WINDOW_MARGIN: f32 = 1.25;
+const MIN_WINDOW: f32 = 1.0; // milliseconds
+const SCOPE_HEIGHT: f32 = 48.0;
+
+#[derive(Debug)]
+struct LineScope {
+    // [столбец] минимум и максимум по всем каналам, от старых сэмплов к новым
+    bins: [[AtomicF32; 2]; SCOPE_BINS],
+    // длина окна в сэмплах, 0 - снимка нет
+    window: AtomicF32,
+    // задержка первого канала в сэмплах
+    delay: AtomicF32,
+    write_pos: AtomicUsize,
+    buffer_size: AtomicUsize,
+}
+
+impl Default for LineScope {
+    fn default() -> Self {
+        Self {
+            bins: std::array::from_fn(|_| Default::default()),
+            window: Default::default(),
+            delay: Default::default(),
+            write_pos: Default::default(),
+            buffer_size: Default::default(),
+        }
+    }
+}
+
+impl LineScope {
+    fn store(&self, line: &DelayLine) {
+        let (Some(&delay), Some(&write_pos)) = (line.delay.first(), line.current_arrow_pos.first())
+        else {
+            return;
+        };
+        let buffer_size = line.delay_buffer_size;
+        let window = (delay * WINDOW_MARGIN)
+            .max(MIN_WINDOW * line.samplerate / 1e3)
+            .min(buffer_size.saturating_sub(1) as f32);
+        let window_len = window as usize;
+
+        for (bin_idx, bin) in self.bins.iter().enumerate() {
+            // сколько сэмплов назад начинается и кончается столбец; последний записан на write_pos - 1
+            let from = window_len - bin_idx * window_len / SCOPE_BINS;
+            let to = (window_len - (bin_idx + 1) * window_len / SCOPE_BINS).max(1);
+            let stride = (from.saturating_sub(to) / MAX_SAMPLES_PER_BIN).max(1);
+            let (min, max) = (to..=from.max(to))
+                .step_by(stride)
+                .flat_map(|samples_ago| {
+                    let idx = (write_pos - samples_ago as isize).rem_euclid(buffer_size as isize);
+                    line.channel_delay_buffer
+                        .iter()
+                        .map(move |buffer| buffer[idx as usize])
+                })
+                .fold((0.0f32, 0.0f32), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+            bin[0].store(min, Ordering::Relaxed);
+            bin[1].store(max, Ordering::Relaxed);
+        }
+        self.window.store(window, Ordering::Relaxed);
+        self.delay.store(delay, Ordering::Relaxed);
+        self.write_pos.store(write_pos as usize, Ordering::Relaxed);
+        self.buffer_size.store(buffer_size, Ordering::Relaxed);
+    }
+}
+
+// Снимки буферов всех линий для редактора
+#[derive(Debug)]
+pub struct ScopeSnapshot {
+    lines: [LineScope; MAX_LINES],
+    samplerate: AtomicF32,
+}
+
+impl Default for ScopeSnapshot {
+    fn default() -> Self {
+        Self {
+            lines: std::array::from_fn(|_| Default::default()),
+            samplerate: Default::default(),
+        }
+    }
+}
+
+// Счетчик в аудиопотоке: снимок раз в 1 / SNAPSHOT_RATE секунды
+#[derive(Debug, Default)]
+pub struct ScopeProbe {
+    samplerate: f32,
+    samples_left: usize,
+}
+
+impl ScopeProbe {
+    pub fn init(&mut self, samplerate: f32) {
+        self.samplerate = samplerate;
+        self.samples_left = 0;
+    }
+
+    // Конец блока. Снимок делается, только когда редактор открыт
+    pub fn update(
+        &mut self,
+        snapshot: &ScopeSnapshot,
+        engine: &MatrixDelayEngine,
+        line_count: usize,
+        block_len: usize,
+        editor_open: bool,
+    ) {
+        if self.samples_left > block_len {
+            self.samples_left -= block_len;
+            return;
+        }
+        if !editor_open {
+            return;
+        }
+        self.samples_left = (self.samplerate / SNAPSHOT_RATE) as usize;
+        snapshot
+            .samplerate
+            .store(self.samplerate, Ordering::Relaxed);
+        for (line_idx, line_scope) in snapshot.lines.iter().enumerate().take(line_count) {
+            line_scope.store(engine.delay_line(line_idx));
+        }
+    }
+}
+
+// Огибающая каждой линии: справа головка записи (самый новый сэмпл), влево - прошлое
+pub fn scope_panel(
+    ui: &mut egui::Ui,
+    snapshot: &ScopeSnapshot,
+    line_names: &[&str],
+    line_colors: &[Color32],
+) {
+    let samplerate = snapshot.samplerate.load(Ordering::Relaxed);
+    for (line_idx, (line_name, line_scope)) in line_names.iter().zip(&snapshot.lines).enumerate() {
+        let window = line_scope.window.load(Ordering::Relaxed);
+        let delay = line_scope.delay.load(Ordering::Relaxed);
+        let color = line_colors
+            .get(line_idx)
+            .copied()
+            .unwrap_or(ui.visuals().text_color());
+
+        ui.horizontal(|ui| {
+            ui.add_sized(
+                vec2(28.0, SCOPE_HEIGHT),
+                egui::Label::new(egui::RichText::new(*line_name).strong().color(color)),
+            );
+            let (rect, response) =
+                ui.allocate_exact_size(vec2(ui.available_width(), SCOPE_HEIGHT), Sense::hover());
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 2.0, Color32::from_gray(20));
+            painter.hline(
+                rect.x_range(),
+                rect.center().y,
+                Stroke::new(1.0, Color32::from_gray(60)),
+            );
+            if window <= 0.0 || samplerate <= 0.0 {
+                return;
+            }
+
+            // масштаб по наибольшему отсчету, чтобы тихий хвост петли тоже был виден
+            let bins: Vec<(f32, f32)> = line_scope
+                .bins
+                .iter()
+                .map(|bin| {
+                    (
+                        bin[0].load(Ordering::Relaxed),
+                        bin[1].load(Ordering::Relaxed),
+                    )
+                })
+                .collect();
+            let scale = bins
+                .iter()
+                .fold(0.0f32, |peak, (min, max)| peak.max(-min).max(*max))
+                .max(1e-6);
+            let bin_width = rect.width() / SCOPE_BINS as f32;
+            for (bin_idx, (min, max)) in bins.iter().enumerate() {
+                let x = rect.left() + (bin_idx as f32 + 0.5) * bin_width;
+                let y_of = |value: f32| rect.center().y - value / scale * rect.height() * 0.45;
+                painter.line_segment(
+                    [pos2(x, y_of(*max)), pos2(x, y_of(*min) + 0.5)],
+                    Stroke::new(bin_width.max(1.0), color.gamma_multiply(0.8)),
+                );
+            }
+
+            // головка записи справа, чтение - на задержку левее
+            painter.vline(
+                rect.right() - 1.0,
+                rect.y_range(),
+                Stroke::new(2.0, Color32::WHITE),
+            );
+            let read_x = rect.right() - rect.width() * (delay / window).min(1.0);
+            painter.vline(read_x, rect.y_range(), Stroke::new(2.0, Color32::YELLOW));
+            painter.text(
+                pos2(read_x + 3.0, rect.top() + 2.0),
+                Align2::LEFT_TOP,
+                format!("read {:.2} ms", delay / samplerate * 1e3),
+                FontId::proportional(9.0),
+                Color32::YELLOW,
+            );
+
+            let write_pos = line_scope.write_pos.load(Ordering::Relaxed);
+            let buffer_size = line_scope.buffer_size.load(Ordering::Relaxed);
+            response.on_hover_text(format!(
+                "Last {:.2} ms of the buffer\nWrite head {write_pos} of {buffer_size}\nRead {:.1} samples behind",
+                window / samplerate * 1e3,
+                delay,
+            ));
+        });
+    }
+}
@@ -0,0 +1,67 @@
+// Настройки standalone-приложения, о которых враппер nih_plug не знает:
+// звуковой файл вместо входа и работа без окна (для проверки на CI без звуковой карты)
+use nih_plug::prelude::*;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::wav::AudioFile;
+
+static INPUT_FILE: OnceLock<AudioFile> = OnceLock::new();
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub fn set_input_file(file: AudioFile) {
+    let _ = INPUT_FILE.set(file);
+}
+
+pub fn set_headless(headless: bool) {
+    HEADLESS.store(headless, Ordering::Relaxed);
+}
+
+pub fn is_headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+// Вызывается на каждом блоке: отсчет --run-for идет с момента, когда звук начал обрабатываться
+pub fn mark_running() {
+    RUNNING.store(true, Ordering::Relaxed);
+}
+
+// Без окна враппер работает, пока не придет Ctrl+C. Через заданное время приложение посылает
+// SIGINT само себе: враппер останавливает звук и выходит из главного цикла как обычно,
+// так что плагин и бэкенд закрываются через свои деструкторы
+#[cfg(unix)]
+pub fn stop_after(duration: Duration) {
+    std::thread::spawn(move || {
+        while !RUNNING.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        std::thread::sleep(duration);
+        // SAFETY: raise только посылает сигнал текущему процессу
+        unsafe {
+            libc::raise(libc::SIGINT);
+        }
+    });
+}
+
+// Подменяет вход плагина содержимым файла, файл проигрывается по кругу.
+// Если каналов в файле меньше, чем у плагина, они повторяются
+pub fn fill_input(buffer: &mut Buffer, position: &mut usize) {
+    let Some(file) = INPUT_FILE.get() else {
+        return;
+    };
+    let file_len = file.len();
+    if file_len == 0 {
+        return;
+    }
+
+    let block_len = buffer.samples();
+    for (channel_idx, samples) in buffer.as_slice().iter_mut().enumerate() {
+        let source = &file.channels[channel_idx % file.channels.len()];
+        for (sample_idx, sample) in samples.iter_mut().enumerate() {
+            *sample = source[(*position + sample_idx) % file_len];
+        }
+    }
+    *position = (*position + block_len) % file_len;
+}
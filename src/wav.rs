@@ -0,0 +1,67 @@
+// Чтение и запись WAV для standalone и офлайн-рендера, каналы хранятся раздельно
+use std::path::Path;
+
+pub struct AudioFile {
+    pub samplerate: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl AudioFile {
+    pub fn len(&self) -> usize {
+        self.channels.first().map_or(0, Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, hound::Error> {
+        let mut reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        let samples: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 * scale))
+                    .collect::<Result<_, _>>()?
+            }
+        };
+
+        // в WAV каналы чередуются
+        let channels_number = spec.channels as usize;
+        let channels = (0..channels_number)
+            .map(|channel_idx| {
+                samples
+                    .iter()
+                    .skip(channel_idx)
+                    .step_by(channels_number)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            samplerate: spec.sample_rate,
+            channels,
+        })
+    }
+
+    // 32-битный float, чтобы хвосты обратной связи выше 0 дБ не обрезались
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), hound::Error> {
+        let spec = hound::WavSpec {
+            channels: self.channels.len() as u16,
+            sample_rate: self.samplerate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for sample_idx in 0..self.len() {
+            for channel in &self.channels {
+                writer.write_sample(channel[sample_idx])?;
+            }
+        }
+        writer.finalize()
+    }
+}
@@ -0,0 +1,43 @@
+use microdelay::params::LINE_IDS;
+use microdelay::preset::param_ids;
+
+#[test]
+fn matrix_keeps_stable_ids() {
+    // ID из первых версий с двумя линиями: по ним хост восстанавливает сохраненные проекты
+    let ids = param_ids();
+    for id in [
+        "in_send_a",
+        "in_send_b",
+        "line_a_feedback",
+        "line_b_feedback",
+        "a_to_b_send",
+        "b_to_a_send",
+        "line_a_level",
+        "line_b_level",
+        "line_a_delay",
+        "line_b_delay",
+    ] {
+        assert!(ids.iter().any(|other| other == id), "{id} is missing");
+    }
+}
+
+#[test]
+fn matrix_has_every_send() {
+    let ids = param_ids();
+    for from in LINE_IDS {
+        assert!(ids.contains(&format!("in_send_{from}")));
+        assert!(ids.contains(&format!("line_{from}_feedback")));
+        for to in LINE_IDS.iter().filter(|to| **to != from) {
+            assert!(ids.contains(&format!("{from}_to_{to}_send")));
+        }
+    }
+}
+
+#[test]
+fn ids_are_unique() {
+    let mut ids = param_ids();
+    let len = ids.len();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), len);
+}
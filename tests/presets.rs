@@ -0,0 +1,106 @@
+use microdelay::engine::stability::spectral_radius;
+use microdelay::engine::tempo_sync::TransportInfo;
+use microdelay::engine::voices::MAX_VOICES;
+use microdelay::engine::{EngineParams, MAX_LINES};
+use microdelay::params::DParams;
+use microdelay::preset::{self, Preset};
+
+fn factory_params(preset: &Preset) -> EngineParams {
+    let mut params = EngineParams::default();
+    preset
+        .apply(&mut params)
+        .unwrap_or_else(|err| panic!("{}: {err}", preset.name));
+    params
+}
+
+#[test]
+fn factory_presets_apply_cleanly() {
+    let bank = preset::factory_bank();
+    assert_eq!(bank.len(), preset::FACTORY_PRESETS.len());
+    for preset in &bank {
+        assert!(!preset.name.is_empty());
+        assert!(!preset.tags.is_empty(), "{}", preset.name);
+        factory_params(preset);
+    }
+
+    let mut names: Vec<&str> = bank.iter().map(|preset| preset.name.as_str()).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), bank.len());
+}
+
+#[test]
+fn factory_presets_feedback_decays() {
+    // Karplus Pluck держится у самой границы, но не переходит ее
+    for preset in preset::factory_bank() {
+        let params = factory_params(&preset);
+        let mut matrix = [[0.0; MAX_LINES]; MAX_LINES];
+        for (row, sends) in matrix.iter_mut().zip(&params.sends) {
+            for (gain, send) in row.iter_mut().zip(sends) {
+                *gain = send / 100.0;
+            }
+        }
+        let loop_gain = spectral_radius(&matrix, params.line_count);
+        assert!(loop_gain < 1.0, "{}: loop gain {loop_gain}", preset.name);
+    }
+}
+
+#[test]
+fn preset_survives_json_round_trip() {
+    for preset in preset::factory_bank() {
+        let json = preset.to_json();
+        assert_eq!(Preset::from_json(&json).unwrap(), preset);
+    }
+}
+
+#[test]
+fn captured_defaults_apply_like_plugin() {
+    // снимок с каждым ID плагина применяется к движку без ошибок и дает те же значения
+    let params = DParams::default();
+    let preset = Preset::capture("Init", &params);
+    let mut ids = preset::param_ids();
+    ids.sort();
+    assert!(preset.params.keys().eq(&ids));
+    let mut engine_params = EngineParams::default();
+    preset.apply(&mut engine_params).unwrap();
+    assert_eq!(
+        engine_params,
+        params.engine_params(TransportInfo::default())
+    );
+}
+
+#[test]
+fn integer_params_are_rounded_and_clamped() {
+    let mut params = EngineParams::default();
+    for (value, voices) in [("2.6", 3), ("0", 1), ("-4", 1), ("64", MAX_VOICES)] {
+        preset::apply_param(&mut params, "voices", value).unwrap();
+        assert_eq!(params.tuning.voices, voices, "{value}");
+    }
+    for (value, interval) in [("7.4", 7.0), ("-4.5", -5.0), ("100", 24.0), ("-100", -24.0)] {
+        preset::apply_param(&mut params, "interval_b", value).unwrap();
+        assert_eq!(params.tuning.interval_b, interval, "{value}");
+    }
+}
+
+#[test]
+fn invalid_values_are_rejected() {
+    let mut params = EngineParams::default();
+    for (id, value) in [
+        ("line_a_sync", "sometimes"),
+        ("line_a_delay", "fast"),
+        ("line_z_delay", "10"),
+        ("line_a_tone", "10"),
+        ("line_a_routing", "sideways"),
+        ("line_a_swap_sends", "maybe"),
+    ] {
+        assert!(
+            preset::apply_param(&mut params, id, value).is_err(),
+            "{id}={value}"
+        );
+    }
+    let newer = format!(
+        r#"{{"version": {}, "params": {{}}}}"#,
+        preset::PRESET_VERSION + 1
+    );
+    assert!(Preset::from_json(&newer).is_err());
+}